@@ -1,22 +1,32 @@
-use crate::{cli::types::AddCmd, config::write::*, prelude::*};
+use crate::{
+    cli::types::AddCmd,
+    config::{journal::Op, settings::CmdDefaults, write::*},
+    prelude::*,
+};
 use rkyv::{de::deserializers::AllocDeserializer, Deserialize};
 
-pub fn insert_new_cmd(args: AddCmd, gen_cmds: &ArchivedGeneratedCommands) -> Result<()> {
+pub fn insert_new_cmd(
+    args: AddCmd,
+    gen_cmds: &ArchivedGeneratedCommands,
+    defaults: &CmdDefaults,
+    cfg: &ConfigDir,
+) -> Result<()> {
     if gen_cmds.contains_key(&args.key) {
         bail!("A command by that key is in the database!")
     }
 
     let gen_cmds = gen_cmds.deserialize(&mut AllocDeserializer)?;
     let aliases = args.aliases.clone();
-    let (key, cmd) = GeneratedCommand::from_args(args);
+    let (key, cmd) = GeneratedCommand::from_args(args, defaults);
 
-    commit_cmd(gen_cmds, (key, cmd), aliases)
+    commit_cmd(gen_cmds, (key, cmd), aliases, cfg)
 }
 
 pub fn commit_cmd(
     mut gen_cmds: GeneratedCommands,
     (key, cmd): (String, GeneratedCommand),
     aliases: Option<Vec<String>>,
+    cfg: &ConfigDir,
 ) -> Result<()> {
     if let Some(ref mut rkyvd_cmds) = gen_cmds.commands {
         if let Some(failed_inserts) = if let Some(ref mut rkyvd_aliases) = gen_cmds.aliases {
@@ -60,7 +70,7 @@ pub fn commit_cmd(
         }
 
         rkyvd_cmds.insert(key, cmd);
-        overwrite_cmds(gen_cmds)
+        overwrite_journaled(cfg, Op::Add, gen_cmds)
     } else {
         let aliases = aliases.map(|mut list| {
             list.drain(..).fold(HashMap::new(), |mut map, alias| {
@@ -71,9 +81,13 @@ pub fn commit_cmd(
         let mut cmds = HashMap::new();
         cmds.insert(key, cmd);
 
-        overwrite_cmds(GeneratedCommands {
-            commands: Some(cmds),
-            aliases,
-        })
+        overwrite_journaled(
+            cfg,
+            Op::Add,
+            GeneratedCommands {
+                commands: Some(cmds),
+                aliases,
+            },
+        )
     }
 }
@@ -0,0 +1,187 @@
+use crate::{
+    cli::types::DiffCmd,
+    config::{
+        backup::list_backups,
+        format::decode,
+        source::{changed_fields, show_value, snapshot, FieldChange, SourceCommand},
+    },
+    prelude::*,
+};
+use rkyv::{de::deserializers::AllocDeserializer, Deserialize};
+use serde::Serialize;
+use std::{collections::BTreeSet, path::Path};
+
+#[derive(Debug, PartialEq, Serialize)]
+#[serde(tag = "change", rename_all = "lowercase")]
+enum KeyDiff {
+    Added {
+        key: String,
+        command: SourceCommand,
+    },
+    Removed {
+        key: String,
+        command: SourceCommand,
+    },
+    Changed {
+        key: String,
+        fields: Vec<FieldChange>,
+    },
+}
+
+#[derive(Serialize)]
+struct Report<'a> {
+    old: &'a str,
+    new: &'a str,
+    changes: &'a [KeyDiff],
+}
+
+/// `current`, `backup:N` as numbered by `restore --list`, or a file in any export format
+fn load(source: &str, cfg: &ConfigDir) -> Result<GeneratedCommands> {
+    let db = if Path::new(source).exists() {
+        decode(source, None)?
+    } else if source == "current" {
+        CmdsDb::from_cfg(cfg)?
+    } else if let Some(n) = source.strip_prefix("backup:") {
+        let backup = n
+            .parse::<usize>()
+            .ok()
+            .and_then(|n| n.checked_sub(1))
+            .and_then(|i| list_backups(cfg).ok()?.into_iter().nth(i))
+            .ok_or_else(|| anyhow!("No backup #{}, try `flurry restore --list`", n))?;
+        CmdsDb::from_path(backup.path)?
+    } else {
+        bail!("No file named {}", source);
+    };
+    Ok(db.archive().deserialize(&mut AllocDeserializer)?)
+}
+
+fn diff_cmds(old: &GeneratedCommands, new: &GeneratedCommands) -> Vec<KeyDiff> {
+    let keys: BTreeSet<&String> = old
+        .commands
+        .iter()
+        .chain(new.commands.iter())
+        .flat_map(|cmds| cmds.keys())
+        .collect();
+
+    keys.into_iter()
+        .filter_map(|key| {
+            let key = key.clone();
+            match (snapshot(old, &key), snapshot(new, &key)) {
+                (None, Some(command)) => Some(KeyDiff::Added { key, command }),
+                (Some(command), None) => Some(KeyDiff::Removed { key, command }),
+                (Some(before), Some(after)) => {
+                    let fields = changed_fields(&before, &after);
+                    if fields.is_empty() {
+                        None
+                    } else {
+                        Some(KeyDiff::Changed { key, fields })
+                    }
+                }
+                (None, None) => None,
+            }
+        })
+        .collect()
+}
+
+fn print_changes(old: &str, new: &str, changes: &[KeyDiff]) {
+    if changes.is_empty() {
+        println!("No differences between {} and {}", old, new);
+        return;
+    }
+
+    println!("--- {}\n+++ {}", old, new);
+    let (mut added, mut removed, mut changed) = (0, 0, 0);
+    for change in changes {
+        match change {
+            KeyDiff::Added { key, command } => {
+                added += 1;
+                println!("+ {:<16} {}", key, command.bin());
+            }
+            KeyDiff::Removed { key, command } => {
+                removed += 1;
+                println!("- {:<16} {}", key, command.bin());
+            }
+            KeyDiff::Changed { key, fields } => {
+                changed += 1;
+                println!("~ {}", key);
+                for FieldChange { field, old, new } in fields {
+                    println!("    {}: {} -> {}", field, show_value(old), show_value(new));
+                }
+            }
+        }
+    }
+    println!("{} added, {} removed, {} changed", added, removed, changed);
+}
+
+pub fn diff_sources(DiffCmd { sources, json }: DiffCmd, cfg: &ConfigDir) -> Result<()> {
+    let (old, new) = match sources.as_slice() {
+        [new] => ("current", new.as_str()),
+        [old, new] => (old.as_str(), new.as_str()),
+        _ => bail!("diff takes one source to compare the current database with, or two"),
+    };
+    let changes = diff_cmds(&load(old, cfg)?, &load(new, cfg)?);
+
+    if json {
+        let report = Report {
+            old,
+            new,
+            changes: &changes,
+        };
+        println!("{}", serde_json::to_string_pretty(&report)?);
+    } else {
+        print_changes(old, new, &changes);
+    }
+    Ok(())
+}
+
+#[test]
+fn reports_changes_per_field() {
+    let cmds = |ls_args: &[&str], extra: Option<&str>, alias: &str| {
+        let mut commands = HashMap::new();
+        commands.insert("ls".to_owned(), GeneratedCommand::running("ls", ls_args));
+        if let Some(key) = extra {
+            commands.insert(
+                key.to_owned(),
+                GeneratedCommand {
+                    filter: FiltersKind::One(FilterKind::Exts(vec!["md".into()])),
+                    ..GeneratedCommand::running(key, &[])
+                },
+            );
+        }
+        let mut aliases = HashMap::new();
+        aliases.insert(alias.to_owned(), "ls".to_owned());
+        GeneratedCommands {
+            commands: Some(commands),
+            aliases: Some(aliases),
+        }
+    };
+
+    let old = cmds(&["-l"], Some("vim"), "l");
+    let new = cmds(&["-l", "-a"], Some("nvim"), "ll");
+    let changes = diff_cmds(&old, &new);
+    let keys: Vec<(&str, &str)> = changes
+        .iter()
+        .map(|change| match change {
+            KeyDiff::Added { key, .. } => ("added", key.as_str()),
+            KeyDiff::Removed { key, .. } => ("removed", key.as_str()),
+            KeyDiff::Changed { key, .. } => ("changed", key.as_str()),
+        })
+        .collect();
+    assert_eq!(
+        keys,
+        vec![("changed", "ls"), ("added", "nvim"), ("removed", "vim")]
+    );
+
+    match &changes[0] {
+        KeyDiff::Changed { fields, .. } => {
+            let names: Vec<&str> = fields.iter().map(|change| change.field.as_str()).collect();
+            assert_eq!(names, vec!["aliases", "args"]);
+        }
+        other => panic!("unexpected {:?}", other),
+    }
+    assert!(diff_cmds(&old, &cmds(&["-l"], Some("vim"), "l")).is_empty());
+
+    let json = serde_json::to_value(&changes[1]).unwrap();
+    assert_eq!(json["change"], "added");
+    assert_eq!(json["command"]["filters"]["exts"][0], "md");
+}
@@ -1,8 +1,231 @@
-use crate::{cli::types::ExportCmd, prelude::*};
+use crate::{
+	cli::types::ExportCmd,
+	config::{
+		crypt::{passphrase, seal},
+		format::{encode, Format},
+		shell::{script, Shell, Source},
+	},
+	prelude::*,
+};
+use regex::Regex;
+use rkyv::{de::deserializers::AllocDeserializer, Deserialize};
+use std::path::PathBuf;
 
-pub fn export_gen_cmds(ExportCmd { output_file }: ExportCmd) -> Result<()> {
-	let existing_cmds_as_bytes = std::fs::read(ConfigPath::Commands.abs())?;
-	std::fs::write(output_file, existing_cmds_as_bytes)?;
-	println!("Export success");
+/// commands picked by key or alias, or matching any of the patterns or tags
+struct Selection {
+	keys: Vec<String>,
+	patterns: Vec<Regex>,
+	tags: Vec<String>,
+}
+
+fn glob_to_regex(glob: &str) -> Regex {
+	let mut pattern = String::from("^");
+	for c in glob.chars() {
+		match c {
+			'*' => pattern.push_str(".*"),
+			'?' => pattern.push('.'),
+			c => pattern.push_str(&regex::escape(&c.to_string())),
+		}
+	}
+	pattern.push('$');
+	Regex::new(&pattern).expect("escaped glob is a valid regex")
+}
+
+impl Selection {
+	fn is_empty(&self) -> bool {
+		self.keys.is_empty() && self.patterns.is_empty() && self.tags.is_empty()
+	}
+
+	fn matches(&self, key: &str, cmd: &GeneratedCommand) -> bool {
+		self.keys.iter().any(|selected| selected == key)
+			|| self.patterns.iter().any(|pattern| pattern.is_match(key))
+			|| cmd.tags.iter().flatten().any(|tag| self.tags.contains(tag))
+	}
+
+	/// only the selected commands and the aliases pointing at them
+	fn apply(mut self, cmds: GeneratedCommands) -> Result<GeneratedCommands> {
+		let GeneratedCommands { commands, aliases } = cmds;
+		let commands = commands.unwrap_or_default();
+		let aliases = aliases.unwrap_or_default();
+
+		for key in self.keys.iter_mut() {
+			if let Some(target) = aliases.get(key) {
+				*key = target.clone();
+			} else if !commands.contains_key(key) {
+				bail!("No command or alias named `{}`", key);
+			}
+		}
+
+		let commands: HashMap<String, GeneratedCommand> = commands
+			.into_iter()
+			.filter(|(key, cmd)| self.matches(key, cmd))
+			.collect();
+		if commands.is_empty() {
+			bail!("No commands matched the selection");
+		}
+		let aliases: HashMap<String, String> = aliases
+			.into_iter()
+			.filter(|(_, target)| commands.contains_key(target))
+			.collect();
+
+		Ok(GeneratedCommands {
+			commands: Some(commands),
+			aliases: Some(aliases).filter(|aliases| !aliases.is_empty()),
+		})
+	}
+}
+
+pub fn export_gen_cmds(
+	ExportCmd {
+		output_file,
+		format,
+		shell,
+		encrypt,
+		globs,
+		regex,
+		tag,
+		keys,
+	}: ExportCmd,
+	cfg: &ConfigDir,
+) -> Result<()> {
+	let mut patterns: Vec<Regex> = globs.iter().map(|glob| glob_to_regex(glob)).collect();
+	// checked when parsing the arguments
+	patterns.extend(regex.iter().filter_map(|regex| Regex::new(regex).ok()));
+	let selection = Selection {
+		keys,
+		patterns,
+		tags: tag,
+	};
+
+	let db = CmdsDb::from_cfg(cfg)?;
+	if let Some(shell) = shell {
+		if format.is_some() || encrypt {
+			bail!("--shell can't be combined with --format or --encrypt");
+		}
+		let cmds = db.archive().deserialize(&mut AllocDeserializer)?;
+		let cmds = if selection.is_empty() {
+			cmds
+		} else {
+			selection.apply(cmds)?
+		};
+		return export_script(shell, &cmds, output_file, cfg);
+	}
+
+	let output_file = output_file.unwrap_or_else(|| PathBuf::from("flurry_exports.db"));
+	let format = format
+		.or_else(|| Format::from_extension(&output_file))
+		.unwrap_or(Format::Rkyv);
+	let bytes = if selection.is_empty() {
+		encode(&db, format)?
+	} else {
+		let cmds = db.archive().deserialize(&mut AllocDeserializer)?;
+		encode(&CmdsDb::from_cmds(&selection.apply(cmds)?)?, format)?
+	};
+
+	if encrypt {
+		let bundle = seal(&bytes, &passphrase(true)?)?;
+		std::fs::write(&output_file, bundle)?;
+		println!(
+			"Exported encrypted {} to {}",
+			format.as_ref(),
+			output_file.display()
+		);
+		return Ok(());
+	}
+
+	std::fs::write(&output_file, bytes)?;
+	println!("Exported {} to {}", format.as_ref(), output_file.display());
+	Ok(())
+}
+
+fn export_script(
+	shell: Shell,
+	cmds: &GeneratedCommands,
+	output_file: Option<PathBuf>,
+	cfg: &ConfigDir,
+) -> Result<()> {
+	// scripts are sourced from anywhere, so a relative `--db` is resolved now
+	let root = cfg.custom_root().map(std::path::absolute).transpose()?;
+	let source = Source {
+		root: root.as_deref(),
+		profile: cfg.profile(),
+	};
+	let (script, skipped) = script(shell, cmds, source);
+	for name in skipped {
+		eprintln!(
+			"`{}` isn't a valid {} function name, skipped",
+			name,
+			shell.as_ref()
+		);
+	}
+	match output_file {
+		Some(output_file) => {
+			std::fs::write(&output_file, script)?;
+			eprintln!("Exported {} to {}", shell.as_ref(), output_file.display());
+		}
+		None => print!("{}", script),
+	}
 	Ok(())
 }
+
+#[test]
+fn selects_by_key_pattern_and_tag() {
+	let cmd = |tags: &[&str]| GeneratedCommand {
+		tags: Some(tags.iter().map(|tag| tag.to_string()).collect()),
+		..GeneratedCommand::running("true", &[])
+	};
+	let cmds = || {
+		let mut commands = HashMap::new();
+		commands.insert("music".to_owned(), cmd(&["media"]));
+		commands.insert("video".to_owned(), cmd(&["media"]));
+		commands.insert("ls".to_owned(), cmd(&[]));
+		commands.insert("lsd".to_owned(), cmd(&[]));
+		commands.insert("vim".to_owned(), cmd(&[]));
+		let mut aliases = HashMap::new();
+		aliases.insert("v".to_owned(), "vim".to_owned());
+		aliases.insert("m".to_owned(), "music".to_owned());
+		GeneratedCommands {
+			commands: Some(commands),
+			aliases: Some(aliases),
+		}
+	};
+
+	let selected = |keys: &[&str], globs: &[&str], tags: &[&str]| {
+		let selection = Selection {
+			keys: keys.iter().map(|key| key.to_string()).collect(),
+			patterns: globs.iter().map(|glob| glob_to_regex(glob)).collect(),
+			tags: tags.iter().map(|tag| tag.to_string()).collect(),
+		};
+		let mut keys: Vec<String> = selection
+			.apply(cmds())
+			.unwrap()
+			.commands
+			.unwrap()
+			.into_keys()
+			.collect();
+		keys.sort();
+		keys
+	};
+
+	assert_eq!(selected(&["v"], &["l?"], &[]), vec!["ls", "vim"]);
+	assert_eq!(
+		selected(&[], &["ls*"], &["media"]),
+		vec!["ls", "lsd", "music", "video"]
+	);
+
+	let only_vim = Selection {
+		keys: vec!["vim".into()],
+		patterns: vec![],
+		tags: vec![],
+	}
+	.apply(cmds())
+	.unwrap();
+	assert_eq!(only_vim.aliases.unwrap().get("v").unwrap(), "vim");
+	assert!(Selection {
+		keys: vec!["nope".into()],
+		patterns: vec![],
+		tags: vec![]
+	}
+	.apply(cmds())
+	.is_err());
+}
@@ -1,7 +1,104 @@
-use crate::{cli::types::GoCmd, config::types::*, prelude::*};
+use crate::{
+    cli::types::GoCmd,
+    config::{settings::Settings, shell::Shell, types::*},
+    prelude::*,
+    utils::{cmd::Plan, os::confirm},
+};
+use serde::Serialize;
+use std::{collections::BTreeMap, env::current_dir, ffi::OsString};
 
-pub fn dispatch_from_args(args: GoCmd, cmds: &ArchivedGeneratedCommands) -> Result<()> {
-    cmds.get(&args.command)
-        .ok_or(anyhow!("No command found by that key"))
-        .and_then(|cmd| cmd.try_exec(&args))
+#[derive(Serialize)]
+struct Resolved<'a> {
+    key: &'a str,
+    bin: String,
+    args: Vec<String>,
+    cwd: String,
+    /// variables set for the command, `None` when it's unset
+    env: BTreeMap<String, Option<String>>,
+    /// files matched by the scan, `None` for commands without one
+    files: Option<usize>,
+    stdin: Option<String>,
+    detach: bool,
+    root: bool,
+}
+
+fn print_plan(GoCmd { command, json, .. }: &GoCmd, plan: Plan) -> Result<()> {
+    let lossy = |arg: &OsString| arg.to_string_lossy().into_owned();
+    let resolved = Resolved {
+        key: command,
+        bin: plan.bin.to_string_lossy().into_owned(),
+        args: plan.args.iter().map(lossy).collect(),
+        cwd: match plan.cwd {
+            Some(cwd) => cwd,
+            None => current_dir()?,
+        }
+        .to_string_lossy()
+        .into_owned(),
+        env: plan.env.into_iter().collect(),
+        files: plan.files,
+        stdin: plan.stdin,
+        detach: plan.detach,
+        root: plan.root,
+    };
+    if *json {
+        println!("{}", serde_json::to_string_pretty(&resolved)?);
+        return Ok(());
+    }
+
+    let quoted: Vec<String> = std::iter::once(&resolved.bin)
+        .chain(&resolved.args)
+        .map(|arg| Shell::Bash.quote(arg))
+        .collect();
+    println!("{:<7} {}", "bin", resolved.bin);
+    println!("{:<7} {}", "argv", quoted.join(" "));
+    println!("{:<7} {}", "cwd", resolved.cwd);
+    for (key, value) in &resolved.env {
+        match value {
+            Some(value) => println!("{:<7} {}={}", "env", key, Shell::Bash.quote(value)),
+            None => println!("{:<7} {} unset", "env", key),
+        }
+    }
+    if let Some(files) = resolved.files {
+        println!("{:<7} {} matched by the scan", "files", files);
+    }
+    if let Some(stdin) = &resolved.stdin {
+        println!("{:<7} {}", "stdin", stdin);
+    }
+    if resolved.detach {
+        println!("{:<7} yes, flurry won't wait for it", "detach");
+    }
+    if resolved.root {
+        println!("{:<7} required", "root");
+    }
+    Ok(())
+}
+
+pub fn dispatch_from_args(
+    args: GoCmd,
+    cmds: &ArchivedGeneratedCommands,
+    settings: &Settings,
+    cfg: &ConfigDir,
+) -> Result<()> {
+    // keys missing from a profile resolve against the default set
+    let fallback = match cfg.profile() {
+        Some(_) if settings.profile_fallback => {
+            Some(CmdsDb::for_lookup(&cfg.clone().with_profile(None))?)
+        }
+        _ => None,
+    };
+    let mut chain = vec![cmds];
+    chain.extend(fallback.as_ref().map(CmdsDb::archive));
+
+    let cmd = ArchivedGeneratedCommands::get_chained(&chain, &args.command)
+        .ok_or(anyhow!("No command found by that key"))?;
+    if args.dry_run {
+        return print_plan(&args, cmd.plan(&args)?);
+    }
+    if settings.confirm_run && !confirm(format!("Run `{}` ({})?", args.command, cmd.bin)) {
+        return Ok(());
+    }
+    match cmd.try_exec(&args)? {
+        0 => Ok(()),
+        code => std::process::exit(code),
+    }
 }
@@ -0,0 +1,138 @@
+use crate::{
+    cli::types::{HistoryCmd, UndoCmd},
+    config::{
+        journal::{self, apply, read_journal, Entry, Op},
+        source::{changed_fields, snapshot},
+        write::overwrite_cmds,
+    },
+    prelude::*,
+    utils::time::{fmt_utc, now_millis},
+};
+use rkyv::{de::deserializers::AllocDeserializer, Deserialize};
+use std::collections::HashSet;
+
+/// numbers of the changes already reverted by an undo
+fn undone(entries: &[Entry]) -> HashSet<usize> {
+    entries.iter().filter_map(|entry| entry.undoes).collect()
+}
+
+fn describe(entry: &Entry) -> String {
+    match (&entry.before, &entry.after) {
+        (None, _) => "created".into(),
+        (_, None) => "removed".into(),
+        (Some(before), Some(after)) => {
+            let fields: Vec<String> = changed_fields(before, after)
+                .into_iter()
+                .map(|change| change.field)
+                .collect();
+            format!("changed {}", fields.join(", "))
+        }
+    }
+}
+
+/// the key for changes to a single command, how many were touched otherwise
+fn subject(change: &[Entry]) -> String {
+    match change {
+        [entry] => entry.key.clone(),
+        _ => format!("{} commands", change.len()),
+    }
+}
+
+pub fn show_history(HistoryCmd { count }: HistoryCmd, cfg: &ConfigDir) -> Result<()> {
+    let entries = read_journal(cfg)?;
+    if entries.is_empty() {
+        println!("No changes recorded yet");
+        return Ok(());
+    }
+
+    let undone = undone(&entries);
+    for change in journal::changes(&entries).into_iter().rev().take(count) {
+        let first = &change[0];
+        let mut summary = match (first.undoes, change) {
+            (Some(n), _) => format!("reverts #{}", n),
+            (None, [entry]) => describe(entry),
+            (None, _) => String::new(),
+        };
+        if undone.contains(&first.change) {
+            summary = format!("{} (undone)", summary).trim_start().to_owned();
+        }
+        let line = format!(
+            "{:>4}  {} UTC  {:<7} ",
+            first.change,
+            fmt_utc(first.at),
+            first.op.as_ref()
+        );
+        println!("{}{:<16} {}", line, subject(change), summary);
+        if change.len() > 1 {
+            for entry in change {
+                println!(
+                    "{:w$}{:<16} {}",
+                    "",
+                    entry.key,
+                    describe(entry),
+                    w = line.len()
+                );
+            }
+        }
+    }
+    Ok(())
+}
+
+/// reverts the last `count` changes, every command a change touched at once
+pub fn undo_changes(
+    UndoCmd { count }: UndoCmd,
+    gen_cmds: &ArchivedGeneratedCommands,
+    cfg: &ConfigDir,
+) -> Result<()> {
+    let entries = read_journal(cfg)?;
+    let undone = undone(&entries);
+    let targets: Vec<&[Entry]> = journal::changes(&entries)
+        .into_iter()
+        .rev()
+        .filter(|change| change[0].op != Op::Undo && !undone.contains(&change[0].change))
+        .take(count)
+        .collect();
+    if targets.is_empty() {
+        bail!("Nothing to undo");
+    }
+
+    let mut gen_cmds = gen_cmds.deserialize(&mut AllocDeserializer)?;
+    let at = now_millis();
+    let mut reverted = vec![];
+    for (i, change) in targets.into_iter().enumerate() {
+        let n = change[0].change;
+        // newest first so a command touched twice in one change ends up as it was before it
+        for entry in change.iter().rev() {
+            let key = &entry.key;
+            let current = snapshot(&gen_cmds, key);
+            if current != entry.after {
+                eprintln!("`{}` was changed after #{}, reverting it anyway", key, n);
+            }
+            for alias in apply(&mut gen_cmds, key, entry.before.clone()) {
+                eprintln!(
+                    "alias `{}` is now taken and was not restored to `{}`",
+                    alias, key
+                );
+            }
+
+            reverted.push(Entry {
+                change: i,
+                op: Op::Undo,
+                key: key.clone(),
+                at,
+                undoes: Some(n),
+                after: snapshot(&gen_cmds, key),
+                before: current,
+            });
+        }
+        println!(
+            "Reverted #{} ({} {})",
+            n,
+            change[0].op.as_ref(),
+            subject(change)
+        );
+    }
+
+    overwrite_cmds(cfg, gen_cmds)?;
+    journal::append(cfg, reverted)
+}
@@ -1,107 +1,642 @@
 use crate::{
 	cli::types::ImportCmd,
-	config::{types::*, write::*},
+	config::{
+		format::{decode, Format},
+		journal::Op,
+		shell::{parse_aliases, Shell},
+		write::overwrite_journaled,
+	},
 	prelude::*,
-	utils::os::query_stdin,
+	utils::os::{
+		linux::{installed_applications, Application},
+		query_stdin,
+	},
 };
-use rkyv::{
-	core_impl::ArchivedOption,
-	de::deserializers::AllocDeserializer,
-	std_impl::{chd::ArchivedHashMap, ArchivedString},
-	Deserialize,
+use rkyv::{de::deserializers::AllocDeserializer, Deserialize};
+use std::{
+	collections::{BTreeMap, HashSet},
+	fs::read_to_string,
+	path::Path,
 };
 
-fn duplicate_checker<'a>(
-	existing_cmds: &ArchivedHashMap<ArchivedString, ArchivedGeneratedCommand>,
-	import_cmds: &'a ArchivedHashMap<ArchivedString, ArchivedGeneratedCommand>,
-) -> Option<Vec<(&'a ArchivedString, String)>> {
-	let mut found_dups: Option<Vec<(&ArchivedString, String)>> = None;
-	for (key, cmd) in import_cmds.iter() {
-		if existing_cmds.contains_key(key) {
-			if let Some(replacement_key) = query_stdin(format!(
-				"Command `{}` exists, targeting `{}`.\nEnter a replacement command trigger or skip import.",
-				key,
-				cmd.bin,
-				)) {
-				match found_dups {
-					Some(ref mut dups) => dups.push((key, replacement_key)),
-					None => {
-						found_dups.replace(vec![(key, replacement_key)]);
-					}
+/// how `import` resolves an imported key that's already taken by a command or alias
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum OnConflict {
+	Fail,
+	Overwrite,
+	Prompt,
+	RenameSuffix,
+	Skip,
+}
+
+#[derive(Debug, PartialEq)]
+enum Action {
+	Add,
+	Overwrite,
+	Rename(String),
+	Skip,
+	/// left to a prompt, which a dry run doesn't show
+	Prompt,
+}
+
+impl Action {
+	fn describe(&self, key: &str) -> String {
+		match self {
+			Action::Add => format!("add        {}", key),
+			Action::Overwrite => format!("overwrite  {}", key),
+			Action::Rename(new_key) => format!("rename     {} -> {}", key, new_key),
+			Action::Skip => format!("skip       {}", key),
+			Action::Prompt => format!("prompt     {}", key),
+		}
+	}
+}
+
+/// what happens to each imported command, and to each imported alias along with the key it
+/// ends up pointing at. Aliases of commands that aren't imported are dropped.
+struct Plan {
+	commands: Vec<(String, Action)>,
+	aliases: Vec<(String, String, Action)>,
+	dropped: Vec<(String, String)>,
+}
+
+struct Planner<'db> {
+	existing_db: &'db GeneratedCommands,
+	on_conflict: OnConflict,
+	dry_run: bool,
+	/// keys taken by the import so far
+	claimed: HashSet<String>,
+	conflicts: Vec<String>,
+}
+
+impl Planner<'_> {
+	fn is_taken(&self, key: &str) -> bool {
+		self.existing_db.contains_key(key) || self.claimed.contains(key)
+	}
+
+	fn prompt_for_key(&self, query: String) -> Action {
+		loop {
+			match query_stdin(format!(
+				"{}\nEnter a replacement key, ! to overwrite it, or nothing to skip it.",
+				query
+			))
+			.as_deref()
+			{
+				None => return Action::Skip,
+				Some("!") => return Action::Overwrite,
+				Some(new_key) if self.is_taken(new_key) => {
+					println!("`{}` is taken as well", new_key)
 				}
+				Some(new_key) => return Action::Rename(new_key.to_owned()),
 			}
 		}
 	}
 
-	found_dups
+	/// `overwritable` is false for aliases colliding with a command, those are never replaced
+	fn resolve(&mut self, key: &str, overwritable: bool, query: String) -> Action {
+		let action = if !self.is_taken(key) {
+			Action::Add
+		} else {
+			match self.on_conflict {
+				OnConflict::Fail => {
+					self.conflicts.push(key.to_owned());
+					Action::Skip
+				}
+				OnConflict::Overwrite if overwritable => Action::Overwrite,
+				OnConflict::Overwrite => Action::Skip,
+				OnConflict::Prompt if self.dry_run => Action::Prompt,
+				OnConflict::Prompt => match self.prompt_for_key(query) {
+					Action::Overwrite if !overwritable => {
+						println!("`{}` is a command and is kept", key);
+						Action::Skip
+					}
+					action => action,
+				},
+				OnConflict::RenameSuffix => Action::Rename(
+					(2..)
+						.map(|n| format!("{}-{}", key, n))
+						.find(|new_key| !self.is_taken(new_key))
+						.unwrap_or_default(),
+				),
+				OnConflict::Skip => Action::Skip,
+			}
+		};
+		match action {
+			Action::Add | Action::Overwrite => self.claimed.insert(key.to_owned()),
+			Action::Rename(ref new_key) => self.claimed.insert(new_key.clone()),
+			Action::Skip | Action::Prompt => false,
+		};
+		action
+	}
 }
 
-fn insert_cmds(
-	existing_db: &ArchivedGeneratedCommands,
-	import_cmds: &ArchivedHashMap<ArchivedString, ArchivedGeneratedCommand>,
-) -> Result<GeneratedCommands> {
-	let mut existing_db = existing_db.deserialize(&mut AllocDeserializer)?;
-	let mut import_cmds = import_cmds.deserialize(&mut AllocDeserializer)?;
+fn plan_import(
+	existing_db: &GeneratedCommands,
+	import_cmds: &BTreeMap<String, GeneratedCommand>,
+	import_aliases: &BTreeMap<String, String>,
+	on_conflict: OnConflict,
+	dry_run: bool,
+) -> Result<Plan> {
+	let mut planner = Planner {
+		existing_db,
+		on_conflict,
+		dry_run,
+		// keys imported as they are are claimed up front so renames can't land on them
+		claimed: import_cmds
+			.keys()
+			.filter(|key| !existing_db.contains_key(key))
+			.cloned()
+			.collect(),
+		conflicts: vec![],
+	};
 
-	if let Some(ref mut existing_cmds) = existing_db.commands {
-		existing_cmds.reserve(import_cmds.capacity());
-		for (key, cmd) in import_cmds.drain() {
-			existing_cmds.insert(key, cmd);
+	let mut commands = vec![];
+	for (key, cmd) in import_cmds {
+		let query = format!(
+			"`{}` already exists, the imported command runs `{}`.",
+			key, cmd.bin
+		);
+		let action = if existing_db.contains_key(key) {
+			planner.resolve(key, true, query)
+		} else {
+			Action::Add
+		};
+		commands.push((key.clone(), action));
+	}
+
+	let mut aliases = vec![];
+	let mut dropped = vec![];
+	for (alias, target) in import_aliases {
+		let target = match commands.iter().find(|(key, _)| key == target) {
+			Some((key, Action::Add)) | Some((key, Action::Overwrite)) => key.clone(),
+			Some((_, Action::Rename(new_key))) => new_key.clone(),
+			// kept under its key unless the prompt renames it
+			Some((key, Action::Prompt)) => key.clone(),
+			// the command it pointed at wasn't imported
+			Some((_, Action::Skip)) | None => {
+				dropped.push((alias.clone(), target.clone()));
+				continue;
+			}
+		};
+
+		let existing_target = existing_db.aliases.as_ref().and_then(|map| map.get(alias));
+		let action = if existing_target == Some(&target) {
+			Action::Add
+		} else {
+			// the import's own aliases are unique, so anything it claimed already is a command
+			let is_command = existing_db
+				.commands
+				.iter()
+				.any(|cmds| cmds.contains_key(alias))
+				|| planner.claimed.contains(alias);
+			let query = format!("alias `{}` of `{}` is already taken.", alias, target);
+			planner.resolve(alias, !is_command, query)
+		};
+		aliases.push((alias.clone(), target, action));
+	}
+
+	if !planner.conflicts.is_empty() {
+		bail!(
+			"`{}` already exist, nothing was imported",
+			planner.conflicts.join("`, `")
+		);
+	}
+	Ok(Plan {
+		commands,
+		aliases,
+		dropped,
+	})
+}
+
+/// removes `key` from the alias map and from its owner's alias list
+fn unlink_alias(
+	commands: &mut HashMap<String, GeneratedCommand>,
+	aliases: &mut HashMap<String, String>,
+	key: &str,
+) {
+	if let Some(owner) = aliases.remove(key) {
+		if let Some(owner_aliases) = commands
+			.get_mut(&owner)
+			.and_then(|cmd| cmd.aliases.as_mut())
+		{
+			owner_aliases.retain(|alias| alias != key);
 		}
-	} else {
-		existing_db.commands.replace(import_cmds);
+		if let Some(cmd) = commands.get_mut(&owner) {
+			cmd.aliases = cmd.aliases.take().filter(|aliases| !aliases.is_empty());
+		}
+	}
+}
+
+fn apply_plan(
+	existing_db: &mut GeneratedCommands,
+	import_cmds: BTreeMap<String, GeneratedCommand>,
+	plan: &Plan,
+) {
+	let GeneratedCommands { commands, aliases } = existing_db;
+	let commands = commands.get_or_insert_with(HashMap::new);
+	let aliases = aliases.get_or_insert_with(HashMap::new);
+
+	let mut imported = vec![];
+	for ((key, cmd), (_, action)) in import_cmds.into_iter().zip(&plan.commands) {
+		let key = match action {
+			Action::Add | Action::Overwrite => key,
+			Action::Rename(new_key) => new_key.clone(),
+			Action::Skip | Action::Prompt => continue,
+		};
+
+		// an overwritten alias stops pointing at its command
+		unlink_alias(commands, aliases, &key);
+		commands.insert(key.clone(), cmd);
+		imported.push(key);
 	}
 
-	Ok(existing_db)
+	for (alias, target, action) in &plan.aliases {
+		let alias = match action {
+			Action::Add | Action::Overwrite => alias,
+			Action::Rename(new_alias) => new_alias,
+			Action::Skip | Action::Prompt => continue,
+		};
+		unlink_alias(commands, aliases, alias);
+		if commands.contains_key(target) {
+			aliases.insert(alias.clone(), target.clone());
+		}
+	}
+
+	// the alias map decides, it holds the aliases that made it in along with the ones an
+	// overwritten command already had
+	for key in imported {
+		let mut cmd_aliases: Vec<String> = aliases
+			.iter()
+			.filter(|(_, target)| **target == key)
+			.map(|(alias, _)| alias.clone())
+			.collect();
+		cmd_aliases.sort();
+		if let Some(cmd) = commands.get_mut(&key) {
+			cmd.aliases = Some(cmd_aliases).filter(|aliases| !aliases.is_empty());
+		}
+	}
 }
 
-fn insert_and_dedup_cmds(
-	existing_db: &ArchivedGeneratedCommands,
-	import_cmds: &ArchivedHashMap<ArchivedString, ArchivedGeneratedCommand>,
-	mut dups: Vec<(&ArchivedString, String)>,
-) -> Result<GeneratedCommands> {
-	let mut existing_db = existing_db.deserialize(&mut AllocDeserializer)?;
-	if let Some(ref mut existing_cmds) = existing_db.commands {
-		existing_cmds.reserve(import_cmds.len());
+fn count_actions<'a>(actions: impl Iterator<Item = &'a Action> + Clone) -> String {
+	let count = |pred: fn(&Action) -> bool| actions.clone().filter(|action| pred(action)).count();
+	let mut counts = format!(
+		"{} added, {} overwritten, {} renamed, {} skipped",
+		count(|action| *action == Action::Add),
+		count(|action| *action == Action::Overwrite),
+		count(|action| matches!(action, Action::Rename(_))),
+		count(|action| *action == Action::Skip),
+	);
+	match count(|action| *action == Action::Prompt) {
+		0 => {}
+		prompts => counts += &format!(", {} would prompt", prompts),
+	}
+	counts
+}
 
-		for (key, cmd) in import_cmds.iter() {
-			let key = if let Some(i) = dups.iter().position(|(k, _)| &&key.as_ref() == k) {
-				dups.remove(i).1
-			} else {
-				key.deserialize(&mut AllocDeserializer)?
-			};
+impl Plan {
+	fn print(&self) {
+		for (key, action) in &self.commands {
+			println!("{}", action.describe(key));
+		}
+		for (alias, target, action) in &self.aliases {
+			println!("{} (alias of {})", action.describe(alias), target);
+		}
+		for (alias, target) in &self.dropped {
+			println!("drop       {} (alias of {}, not imported)", alias, target);
+		}
+	}
 
-			existing_cmds.insert(key, cmd.deserialize(&mut AllocDeserializer)?);
+	fn summary(&self) -> String {
+		let mut summary = count_actions(self.commands.iter().map(|(_, action)| action));
+		if !self.aliases.is_empty() {
+			summary += "; aliases ";
+			summary += &count_actions(self.aliases.iter().map(|(_, _, action)| action));
+		}
+		if !self.dropped.is_empty() {
+			summary += &format!(
+				"; {} aliases of commands that weren't imported dropped",
+				self.dropped.len()
+			);
+		}
+		summary
+	}
+}
+
+/// the commands and aliases of an exported file
+fn read_export(
+	path: &Path,
+	format: Option<Format>,
+) -> Result<(BTreeMap<String, GeneratedCommand>, BTreeMap<String, String>)> {
+	let import_db = decode(path, format)?
+		.archive()
+		.deserialize(&mut AllocDeserializer)?;
+	Ok((
+		import_db.commands.unwrap_or_default().into_iter().collect(),
+		import_db.aliases.unwrap_or_default().into_iter().collect(),
+	))
+}
+
+/// the aliases defined in a shell startup file, reporting the ones that can't become commands
+fn read_shell_aliases(
+	path: &Path,
+	shell: Option<Shell>,
+) -> Result<BTreeMap<String, GeneratedCommand>> {
+	let shell = shell.unwrap_or_else(|| Shell::from_rc(path));
+	let raw =
+		read_to_string(path).map_err(|e| anyhow!("Unable to read {}: {}", path.display(), e))?;
+	let (commands, skipped) = parse_aliases(shell, &raw);
+	for (n, reason) in skipped {
+		eprintln!("{}:{}: skipped, {}", path.display(), n, reason);
+	}
+	Ok(commands)
+}
+
+/// the applications picked by `--app`, or from a list when there's none. Dry runs don't ask
+/// and plan the import of every application instead.
+fn select_apps(
+	apps: Vec<Application>,
+	selectors: &[String],
+	dry_run: bool,
+) -> Result<Vec<Application>> {
+	if selectors.is_empty() && dry_run {
+		return Ok(apps);
+	}
+	if selectors.is_empty() {
+		for (n, app) in apps.iter().enumerate() {
+			println!("{:>4}  {:<20} {}", n + 1, app.key(), app.name);
+		}
+		let answer = match query_stdin(
+			"Enter the numbers of the applications to import, separated by spaces, or nothing to cancel.",
+		) {
+			Some(answer) => answer,
+			None => bail!("Nothing was imported"),
+		};
+		let mut picked = vec![];
+		for n in answer.split_whitespace() {
+			match n.parse::<usize>() {
+				Ok(n) if (1..=apps.len()).contains(&n) => picked.push(n - 1),
+				_ => bail!("`{}` isn't one of the listed numbers", n),
+			}
 		}
+		return Ok(apps
+			.into_iter()
+			.enumerate()
+			.filter(|(n, _)| picked.contains(n))
+			.map(|(_, app)| app)
+			.collect());
 	}
 
-	Ok(existing_db)
+	let matches = |app: &Application, selector: &str| {
+		[app.key(), app.id.clone(), app.name.clone()]
+			.iter()
+			.any(|name| name.eq_ignore_ascii_case(selector))
+	};
+	if let Some(unknown) = selectors
+		.iter()
+		.find(|selector| !apps.iter().any(|app| matches(app, selector)))
+	{
+		bail!("No installed application named `{}`", unknown);
+	}
+	Ok(apps
+		.into_iter()
+		.filter(|app| selectors.iter().any(|selector| matches(app, selector)))
+		.collect())
+}
+
+/// commands running installed applications, tagged `desktop`. Applications with a window of
+/// their own are detached, terminal ones run in the foreground.
+fn read_desktop_apps(
+	selectors: &[String],
+	dry_run: bool,
+) -> Result<BTreeMap<String, GeneratedCommand>> {
+	let (apps, errors) = installed_applications();
+	for e in errors {
+		eprintln!("skipped {}", e);
+	}
+	if apps.is_empty() {
+		bail!("No installed applications found");
+	}
+
+	let mut commands = BTreeMap::new();
+	for app in select_apps(apps, selectors, dry_run)? {
+		// applications sharing a key, e.g. org.kde.foo and org.gnome.foo
+		let key = match app.key() {
+			key if commands.contains_key(&key) => (2..)
+				.map(|n| format!("{}-{}", key, n))
+				.find(|new_key| !commands.contains_key(new_key))
+				.unwrap_or_default(),
+			key => key,
+		};
+		let mut exec = app.exec.into_iter();
+		commands.insert(
+			key,
+			GeneratedCommand {
+				bin: exec.next().unwrap_or_default(),
+				dfl_args: Some(exec.collect::<Vec<_>>()).filter(|args| !args.is_empty()),
+				tags: Some(vec!["desktop".to_owned()]),
+				// windowed applications shouldn't hold the terminal they were started from
+				detach: !app.terminal,
+				..Default::default()
+			},
+		);
+	}
+	Ok(commands)
 }
 
 pub fn import_cmds_from_file(
-	ImportCmd { file_path }: ImportCmd,
+	ImportCmd {
+		file_path,
+		format,
+		from_shell,
+		shell,
+		desktop,
+		app,
+		on_conflict,
+		dry_run,
+	}: ImportCmd,
 	existing_db: &ArchivedGeneratedCommands,
+	cfg: &ConfigDir,
 ) -> Result<()> {
-	let new_archive = CmdsDb::from_path(file_path)?;
-	let new_cmds = new_archive.archive();
-
-	if let ArchivedOption::Some(ref import_cmds) = new_cmds.commands {
-		if let ArchivedOption::Some(ref existing_cmds) = existing_db.commands {
-			if let Some(dups) = duplicate_checker(existing_cmds, import_cmds) {
-				overwrite_cmds(insert_and_dedup_cmds(existing_db, import_cmds, dups)?)?;
-			} else {
-				overwrite_cmds(insert_cmds(existing_db, import_cmds)?)?;
-			}
-		} else {
-			let mut existing_db = existing_db.deserialize(&mut AllocDeserializer)?;
-			let imported_cmds = new_cmds.commands.deserialize(&mut AllocDeserializer)?;
-			existing_db.commands = imported_cmds;
-			overwrite_cmds(existing_db)?;
+	let (import_cmds, import_aliases) = match (file_path, from_shell, desktop) {
+		(Some(path), None, false) => read_export(&path, format)?,
+		(None, Some(rc), false) => (read_shell_aliases(&rc, shell)?, BTreeMap::new()),
+		(None, None, true) => (read_desktop_apps(&app, dry_run)?, BTreeMap::new()),
+		(None, None, false) => {
+			bail!("Nothing to import, pass a file with -f, --from-shell or --desktop")
 		}
-	} else {
-		seppuku!("No commands to import");
+		_ => bail!("-f, --from-shell and --desktop can't be combined"),
+	};
+	if import_cmds.is_empty() {
+		bail!("No commands to import");
 	}
 
-	println!("Commands imported");
+	let mut existing_db = existing_db.deserialize(&mut AllocDeserializer)?;
+	let plan = plan_import(
+		&existing_db,
+		&import_cmds,
+		&import_aliases,
+		on_conflict,
+		dry_run,
+	)?;
+
+	if dry_run {
+		plan.print();
+		println!("Dry run, nothing was imported: {}", plan.summary());
+		return Ok(());
+	}
+
+	apply_plan(&mut existing_db, import_cmds, &plan);
+	overwrite_journaled(cfg, Op::Import, existing_db)?;
+	for (alias, target) in &plan.dropped {
+		eprintln!(
+			"alias `{}` was dropped, `{}` wasn't imported",
+			alias, target
+		);
+	}
+	let imported = plan
+		.commands
+		.iter()
+		.filter(|(_, action)| *action != Action::Skip)
+		.count();
+	println!(
+		"Imported {} of {} commands: {}",
+		imported,
+		plan.commands.len(),
+		plan.summary()
+	);
 	Ok(())
 }
+
+#[test]
+fn conflicts_follow_strategy() {
+	let cmd = |bin: &str| GeneratedCommand::running(bin, &[]);
+	let mut commands = HashMap::new();
+	commands.insert(
+		"ls".to_owned(),
+		GeneratedCommand {
+			aliases: Some(vec!["l".into(), "lsa".into()]),
+			..cmd("ls")
+		},
+	);
+	commands.insert(
+		"ls-2".to_owned(),
+		GeneratedCommand {
+			aliases: Some(vec!["x".into()]),
+			..cmd("exa")
+		},
+	);
+	let mut aliases = HashMap::new();
+	aliases.insert("l".to_owned(), "ls".to_owned());
+	aliases.insert("lsa".to_owned(), "ls".to_owned());
+	aliases.insert("x".to_owned(), "ls-2".to_owned());
+	let existing_db = GeneratedCommands {
+		commands: Some(commands),
+		aliases: Some(aliases),
+	};
+
+	let mut import_cmds = BTreeMap::new();
+	import_cmds.insert("l".to_owned(), cmd("lsd"));
+	import_cmds.insert("ls".to_owned(), cmd("ls -la"));
+	import_cmds.insert("vim".to_owned(), cmd("vim"));
+	let mut import_aliases = BTreeMap::new();
+	import_aliases.insert("ll".to_owned(), "ls".to_owned());
+	import_aliases.insert("v".to_owned(), "vim".to_owned());
+	import_aliases.insert("x".to_owned(), "vim".to_owned());
+
+	let plan = plan_import(
+		&existing_db,
+		&import_cmds,
+		&import_aliases,
+		OnConflict::RenameSuffix,
+		false,
+	)
+	.unwrap();
+	assert_eq!(
+		plan.commands
+			.iter()
+			.map(|(_, action)| action)
+			.collect::<Vec<_>>(),
+		vec![
+			&Action::Rename("l-2".into()),
+			&Action::Rename("ls-3".into()),
+			&Action::Add
+		]
+	);
+	// aliases follow their renamed command
+	assert_eq!(
+		plan.aliases,
+		vec![
+			("ll".into(), "ls-3".into(), Action::Add),
+			("v".into(), "vim".into(), Action::Add),
+			("x".into(), "vim".into(), Action::Rename("x-2".into())),
+		]
+	);
+	assert!(plan_import(
+		&existing_db,
+		&import_cmds,
+		&import_aliases,
+		OnConflict::Fail,
+		false
+	)
+	.is_err());
+	// dry runs report the prompts instead of asking
+	let plan = plan_import(
+		&existing_db,
+		&import_cmds,
+		&import_aliases,
+		OnConflict::Prompt,
+		true,
+	)
+	.unwrap();
+	assert_eq!(
+		plan.summary(),
+		"1 added, 0 overwritten, 0 renamed, 0 skipped, 2 would prompt; aliases 2 added, 0 overwritten, 0 renamed, 0 skipped, 1 would prompt"
+	);
+
+	let mut overwritten = existing_db;
+	let plan = plan_import(
+		&overwritten,
+		&import_cmds,
+		&import_aliases,
+		OnConflict::Overwrite,
+		false,
+	)
+	.unwrap();
+	apply_plan(&mut overwritten, import_cmds, &plan);
+	let bin = |key| overwritten.get(key).map(|cmd| cmd.bin.as_str());
+	assert_eq!(bin("l"), Some("lsd"));
+	assert_eq!(bin("ll"), Some("ls -la"));
+	assert_eq!(bin("x"), Some("vim"));
+	assert_eq!(overwritten.get("ls-2").unwrap().aliases, None);
+	// the overwritten command keeps its aliases that weren't taken over
+	assert_eq!(
+		overwritten.get("ls").unwrap().aliases,
+		Some(vec!["ll".into(), "lsa".into()])
+	);
+	assert_eq!(
+		plan.summary(),
+		"1 added, 2 overwritten, 0 renamed, 0 skipped; aliases 2 added, 1 overwritten, 0 renamed, 0 skipped"
+	);
+
+	// aliases of skipped commands are reported
+	let import_cmds: BTreeMap<String, GeneratedCommand> =
+		vec![("ls".to_owned(), cmd("ls -la"))].into_iter().collect();
+	let plan = plan_import(
+		&overwritten,
+		&import_cmds,
+		&import_aliases,
+		OnConflict::Skip,
+		false,
+	)
+	.unwrap();
+	assert_eq!(
+		plan.dropped,
+		vec![
+			("ll".into(), "ls".into()),
+			("v".into(), "vim".into()),
+			("x".into(), "vim".into()),
+		]
+	);
+	assert!(plan
+		.summary()
+		.ends_with("; 3 aliases of commands that weren't imported dropped"));
+}
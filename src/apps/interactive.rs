@@ -1,15 +1,54 @@
 use crate::{
-    config::{types::*, write::overwrite_cmds},
+    config::{journal::Op, lock::DbLock, settings::Settings, types::*, write::overwrite_journaled},
     prelude::*,
     tui::prelude::*,
+    utils::{
+        cmd::Plan,
+        os::{confirm, query_stdin},
+    },
 };
 use rkyv::{de::deserializers::AllocDeserializer, Deserialize};
 
-pub fn dispatch_interactive(gen_cmds: &ArchivedGeneratedCommands) -> Result<()> {
+/// applies the commands added, edited or removed during a session (`ours` vs `base`) onto `theirs`
+fn merge_session(
+    base: GeneratedCommands,
+    ours: GeneratedCommands,
+    mut theirs: GeneratedCommands,
+) -> GeneratedCommands {
+    fn merge_map<V: PartialEq>(
+        base: Option<HashMap<String, V>>,
+        ours: Option<HashMap<String, V>>,
+        theirs: &mut Option<HashMap<String, V>>,
+    ) {
+        let base = base.unwrap_or_default();
+        let ours = ours.unwrap_or_default();
+        let merged = theirs.get_or_insert_with(HashMap::new);
+
+        for key in base.keys().filter(|key| !ours.contains_key(*key)) {
+            merged.remove(key);
+        }
+        for (key, val) in ours {
+            if base.get(&key) != Some(&val) {
+                merged.insert(key, val);
+            }
+        }
+        if merged.is_empty() {
+            theirs.take();
+        }
+    }
+
+    merge_map(base.commands, ours.commands, &mut theirs.commands);
+    merge_map(base.aliases, ours.aliases, &mut theirs.aliases);
+    theirs
+}
+
+pub fn dispatch_interactive(cmds_db: &CmdsDb, settings: &Settings, cfg: &ConfigDir) -> Result<()> {
+    let gen_cmds = cmds_db.archive();
     if gen_cmds.commands.is_none() {
         return Err(anyhow!("No commands yet!"));
     }
 
+    let base = gen_cmds.deserialize(&mut AllocDeserializer)?;
     let mut gen_cmds = gen_cmds.deserialize(&mut AllocDeserializer)?;
     let mut exit_status = None;
 
@@ -17,9 +56,11 @@ pub fn dispatch_interactive(gen_cmds: &ArchivedGeneratedCommands) -> Result<()>
         let cmds_ref = RefCell::from(commands);
 
         let mut app = StatefulCmdsTable::with_items(&cmds_ref)
-            .with_header_style(Style::default().fg(Color::Blue))
-            .with_rm_style(Style::default().fg(Color::Red))
-            .with_selection_style(Style::default().fg(Color::Cyan));
+            .with_header_style(settings.tui.header_style())
+            .with_rm_style(settings.tui.rm_style())
+            .with_selection_style(settings.tui.selection_style())
+            .with_poll_rate(settings.tui.poll_rate)
+            .with_defaults(settings.defaults.clone());
 
         let status = app.render(None)?;
         if status.success {
@@ -36,15 +77,45 @@ pub fn dispatch_interactive(gen_cmds: &ArchivedGeneratedCommands) -> Result<()>
 
     if let Some(status) = exit_status {
         if let Some(key) = status.go_request {
-            if let Some(cmd) = gen_cmds.get(&key) {
-                match &cmd.dfl_args {
-                    Some(args) => run_cmd!(@ &cmd.bin => args),
-                    None => run_cmd!(@ &cmd.bin =>),
-                }?;
+            if let Some(cmd) = gen_cmds.get(&key).filter(|cmd| {
+                !settings.confirm_run || confirm(format!("Run `{}` ({})?", key, cmd.bin))
+            }) {
+                // the session is saved once the command exits, even when it couldn't start
+                if let Err(e) = Plan::stored(cmd).and_then(Plan::run) {
+                    eprintln!("Unable to run `{}`: {}", key, e);
+                }
             }
         }
 
-        overwrite_cmds(gen_cmds)?;
+        if gen_cmds == base {
+            return Ok(());
+        }
+
+        let lock = DbLock::acquire(cfg)?;
+        let on_disk = CmdsDb::from_cfg(cfg)?;
+        if on_disk.fingerprint() != cmds_db.fingerprint() {
+            match query_stdin(
+                "The command database was changed by another flurry process while the tui was open.\n\
+                (m)erge your changes into it, (r)eload it and discard your changes, or skip to cancel.",
+            )
+            .as_deref()
+            {
+                Some("m") | Some("merge") => {
+                    let theirs = on_disk.archive().deserialize(&mut AllocDeserializer)?;
+                    gen_cmds = merge_session(base, gen_cmds, theirs);
+                }
+                Some("r") | Some("reload") => {
+                    drop(lock);
+                    return dispatch_interactive(&on_disk, settings, cfg);
+                }
+                _ => {
+                    println!("Changes discarded");
+                    return Ok(());
+                }
+            }
+        }
+
+        overwrite_journaled(cfg, Op::Tui, gen_cmds)?;
     }
     Ok(())
 }
@@ -52,19 +123,45 @@ pub fn dispatch_interactive(gen_cmds: &ArchivedGeneratedCommands) -> Result<()>
 #[test]
 fn poll_state() {
     use std::{
-        fs::write,
+        fs::{remove_dir_all, write},
+        path::PathBuf,
         sync::mpsc::{channel, Receiver, Sender},
         thread,
     };
 
-    let test_output_path = "test_output";
+    /// removed even when an assertion fails
+    struct ScopedDir(PathBuf);
+    impl Drop for ScopedDir {
+        fn drop(&mut self) {
+            let _ = remove_dir_all(&self.0);
+        }
+    }
+
+    // hermetic config dir seeded with one command instead of the user's database
+    let dir = ScopedDir(std::env::temp_dir().join(format!("flurry-poll-{}", std::process::id())));
+    let cfg = ConfigDir::resolve(Some(dir.0.clone()));
+    crate::config::write::init_cmds_if_not_exists(&cfg).unwrap();
+    let mut seed = HashMap::new();
+    seed.insert("ls".to_owned(), GeneratedCommand::default());
+    crate::config::write::overwrite_cmds(
+        &cfg,
+        GeneratedCommands {
+            commands: Some(seed),
+            aliases: None,
+        },
+    )
+    .unwrap();
+
+    let test_output_path = cfg.root().join("test_output");
     let write_to_test_output = move |val: &str| {
-        write(test_output_path, val).seppuku(None);
+        let _ = write(&test_output_path, val);
     };
 
     let (tx, rx): (Sender<usize>, Receiver<usize>) = channel();
-    thread::spawn(move || loop {
-        if let Ok(state) = rx.recv() {
+    let write_state = write_to_test_output.clone();
+    // ends once `tx` is dropped so the dir isn't written to after it's removed
+    let poller = thread::spawn(move || {
+        while let Ok(state) = rx.recv() {
             let state = match state {
                 StatefulCmdsTable::DFL_STATE => "DFL_STATE\n",
                 StatefulCmdsTable::ADD_STATE => "ADD_STATE\n",
@@ -74,11 +171,11 @@ fn poll_state() {
                 _ => "UNDEFINED\n",
             };
 
-            write_to_test_output(state);
+            write_state(state);
         }
     });
 
-    let cmds_db = crate::config::get::CmdsDb::from_cfg().unwrap();
+    let cmds_db = crate::config::get::CmdsDb::from_cfg(&cfg).unwrap();
     let mut cmds_deser = cmds_db
         .archive()
         .deserialize(&mut AllocDeserializer)
@@ -90,7 +187,10 @@ fn poll_state() {
             .with_header_style(Style::default().fg(Color::Blue))
             .with_rm_style(Style::default().fg(Color::Red))
             .with_selection_style(Style::default().fg(Color::Cyan));
-        match app.render(Some(&tx)) {
+        let rendered = app.render(Some(&tx));
+        drop(tx);
+        poller.join().unwrap();
+        match rendered {
             Ok(exit_status) => {
                 write_to_test_output(&format!("{:#?}", &exit_status));
                 assert_eq!(exit_status.success, true);
@@ -99,3 +199,28 @@ fn poll_state() {
         }
     }
 }
+
+#[test]
+fn merge_session_keeps_concurrent_changes() {
+    let cmd = |bin: &str| GeneratedCommand::running(bin, &[]);
+    let db = |cmds: Vec<(&str, &str)>| GeneratedCommands {
+        commands: Some(
+            cmds.into_iter()
+                .map(|(key, bin)| (key.to_owned(), cmd(bin)))
+                .collect(),
+        ),
+        aliases: None,
+    };
+
+    let base = db(vec![("a", "a"), ("b", "b"), ("c", "c")]);
+    // session removed b, edited c and added d
+    let ours = db(vec![("a", "a"), ("c", "c2"), ("d", "d")]);
+    // another process edited a and added e meanwhile
+    let theirs = db(vec![("a", "a2"), ("b", "b"), ("c", "c"), ("e", "e")]);
+
+    let merged = merge_session(base, ours, theirs);
+    assert_eq!(
+        merged,
+        db(vec![("a", "a2"), ("c", "c2"), ("d", "d"), ("e", "e")])
+    );
+}
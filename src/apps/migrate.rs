@@ -0,0 +1,55 @@
+use crate::{
+    cli::types::MigrateCmd,
+    config::{
+        migrate::{version_of, CURRENT, LEGACY},
+        source,
+    },
+    prelude::*,
+    utils::fs::atomic::write_atomic,
+};
+
+pub fn migrate_db(MigrateCmd { check, file_path }: MigrateCmd, cfg: &ConfigDir) -> Result<()> {
+    // databases from before the toml source still sit in commands.toml
+    let adopt = file_path.is_none() && source::is_archive(cfg)?;
+    let path = match file_path {
+        Some(ref path) => path.clone(),
+        None if adopt => ConfigPath::Commands.abs(cfg),
+        None => ConfigPath::Cache.abs(cfg),
+    };
+    let version = version_of(&path)?;
+
+    let status = match version {
+        LEGACY => "legacy format without header, needs migration",
+        v if v == CURRENT => "up to date",
+        v if v < CURRENT => "needs migration",
+        _ => "newer than this build of flurry supports",
+    };
+    println!(
+        "{}: format v{} ({}), current is v{}",
+        path.display(),
+        version,
+        status,
+        CURRENT
+    );
+
+    if version > CURRENT {
+        bail!("Upgrade flurry to read {}", path.display());
+    }
+
+    if check {
+        // make sure the archive actually loads, not just that the header looks right
+        CmdsDb::from_path(&path)?;
+        return Ok(());
+    }
+    if version == CURRENT && !adopt {
+        return Ok(());
+    }
+
+    match file_path {
+        // from_cfg writes the upgraded database back and snapshots the old one
+        None => drop(CmdsDb::from_cfg(cfg)?),
+        Some(ref path) => write_atomic(path, CmdsDb::from_path(path)?.as_bytes())?,
+    }
+    println!("Migrated {} to v{}", path.display(), CURRENT);
+    Ok(())
+}
@@ -1,8 +1,14 @@
 pub mod add;
+pub mod diff;
 pub mod export;
 pub mod go;
+pub mod history;
 pub mod import;
 pub mod interactive;
 pub mod list;
+pub mod migrate;
+pub mod profile;
+pub mod restore;
 pub mod rm;
 pub mod set_attr;
+pub mod sync;
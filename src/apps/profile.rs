@@ -0,0 +1,78 @@
+use crate::{
+    cli::types::{ProfileAction, ProfileCmd, ProfileCreateCmd, ProfileUseCmd},
+    config::{settings::Settings, write::init_cmds_if_not_exists},
+    prelude::*,
+};
+use std::fs::read_dir;
+
+/// names of every profile with a database, the default set first
+pub fn list_profiles(cfg: &ConfigDir) -> Result<Vec<String>> {
+    let mut names = vec![];
+    let dir = ConfigPath::Profiles.abs(cfg);
+    if dir.exists() {
+        for entry in read_dir(dir)? {
+            let path = entry?.path();
+            if !path.join("commands.toml").exists() {
+                continue;
+            }
+            if let Some(name) = path.file_name().and_then(|name| name.to_str()) {
+                names.push(name.to_owned());
+            }
+        }
+    }
+    names.sort();
+    names.insert(0, ConfigDir::DEFAULT_PROFILE.to_owned());
+    Ok(names)
+}
+
+fn activate(name: String, settings: &Settings, cfg: &ConfigDir) -> Result<()> {
+    let profile = Some(name).filter(|name| name != ConfigDir::DEFAULT_PROFILE);
+    Settings {
+        profile,
+        ..settings.clone()
+    }
+    .save(cfg)
+}
+
+pub fn manage_profiles(
+    ProfileCmd { action }: ProfileCmd,
+    settings: &Settings,
+    cfg: &ConfigDir,
+) -> Result<()> {
+    match action {
+        ProfileAction::List(_) => {
+            let active = settings
+                .profile
+                .as_deref()
+                .unwrap_or(ConfigDir::DEFAULT_PROFILE);
+            for name in list_profiles(cfg)? {
+                let marker = if name == active { '*' } else { ' ' };
+                println!("{} {}", marker, name);
+            }
+        }
+        ProfileAction::Create(ProfileCreateCmd {
+            name,
+            activate: switch,
+        }) => {
+            if list_profiles(cfg)?.contains(&name) {
+                bail!("Profile `{}` already exists", name);
+            }
+            init_cmds_if_not_exists(&cfg.clone().with_profile(Some(name.clone())))?;
+            println!("Created profile `{}`", name);
+            if switch {
+                activate(name, settings, cfg)?;
+            }
+        }
+        ProfileAction::Use(ProfileUseCmd { name }) => {
+            if !list_profiles(cfg)?.contains(&name) {
+                bail!(
+                    "No profile named `{}`, create it with `flurry profile create {0}`",
+                    name
+                );
+            }
+            println!("Now using profile `{}`", name);
+            activate(name, settings, cfg)?;
+        }
+    }
+    Ok(())
+}
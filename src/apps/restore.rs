@@ -0,0 +1,67 @@
+use crate::{
+    cli::types::RestoreCmd,
+    config::{
+        backup::{list_backups, Backup},
+        journal::Op,
+        write::overwrite_journaled,
+    },
+    prelude::*,
+    utils::{os::query_stdin, time::fmt_utc},
+};
+use rkyv::{core_impl::ArchivedOption, de::deserializers::AllocDeserializer, Deserialize};
+
+fn describe(index: usize, Backup { path, created }: &Backup) -> String {
+    let contents = match CmdsDb::from_path(path) {
+        Ok(db) => match &db.archive().commands {
+            ArchivedOption::Some(cmds) if cmds.len() == 1 => "1 command".into(),
+            ArchivedOption::Some(cmds) => format!("{} commands", cmds.len()),
+            ArchivedOption::None => "no commands".into(),
+        },
+        Err(e) => format!("unreadable ({})", e),
+    };
+    format!("{:>3}) {} UTC  {}", index + 1, fmt_utc(*created), contents)
+}
+
+pub fn restore_backup(RestoreCmd { list, index }: RestoreCmd, cfg: &ConfigDir) -> Result<()> {
+    let backups = list_backups(cfg)?;
+    if backups.is_empty() {
+        bail!(
+            "No backups found in {}",
+            ConfigPath::Backups.abs(cfg).display()
+        );
+    }
+
+    if list || index.is_none() {
+        for (i, backup) in backups.iter().enumerate() {
+            println!("{}", describe(i, backup));
+        }
+        if list {
+            return Ok(());
+        }
+    }
+
+    let index = match index {
+        Some(index) => index,
+        None => match query_stdin("Enter the number of the backup to restore or skip to cancel.") {
+            Some(input) => input
+                .parse()
+                .map_err(|_| anyhow!("{} is not a backup number", input))?,
+            None => return Ok(()),
+        },
+    };
+
+    let backup = index
+        .checked_sub(1)
+        .and_then(|i| backups.get(i))
+        .ok_or_else(|| anyhow!("No backup #{}, try `flurry restore --list`", index))?;
+
+    // re-serializing upgrades older backups to the current file format,
+    // and overwrite_journaled snapshots the database being replaced
+    let restored = CmdsDb::from_path(&backup.path)?
+        .archive()
+        .deserialize(&mut AllocDeserializer)?;
+    overwrite_journaled(cfg, Op::Restore, restored)?;
+
+    println!("Restored backup from {} UTC", fmt_utc(backup.created));
+    Ok(())
+}
@@ -1,11 +1,15 @@
 use crate::{
     cli::types::RmCmd,
-    config::{types::*, write::overwrite_cmds},
+    config::{journal::Op, types::*, write::overwrite_journaled},
     prelude::*,
 };
 use rkyv::{de::deserializers::AllocDeserializer, Deserialize};
 
-pub fn try_rm_cmd(RmCmd { key, alias }: RmCmd, gen_cmds: &ArchivedGeneratedCommands) -> Result<()> {
+pub fn try_rm_cmd(
+    RmCmd { key, alias }: RmCmd,
+    gen_cmds: &ArchivedGeneratedCommands,
+    cfg: &ConfigDir,
+) -> Result<()> {
     if !gen_cmds.contains_key(&key) {
         return Err(anyhow!("No command or alias by that key"));
     }
@@ -42,5 +46,5 @@ pub fn try_rm_cmd(RmCmd { key, alias }: RmCmd, gen_cmds: &ArchivedGeneratedComma
         }
     }
     println!("Removing {}", key);
-    overwrite_cmds(gen_cmds)
+    overwrite_journaled(cfg, Op::Rm, gen_cmds)
 }
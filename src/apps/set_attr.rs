@@ -1,7 +1,11 @@
-use crate::{cli::types::SetCmd, config::write::*, prelude::*};
+use crate::{
+    cli::types::SetCmd,
+    config::{journal::Op, write::*},
+    prelude::*,
+};
 use rkyv::{de::deserializers::AllocDeserializer, Deserialize};
 
-pub fn edit_cmd(args: SetCmd, gen_cmds: &ArchivedGeneratedCommands) -> Result<()> {
+pub fn edit_cmd(args: SetCmd, gen_cmds: &ArchivedGeneratedCommands, cfg: &ConfigDir) -> Result<()> {
     let SetCmd {
         target,
         bin,
@@ -9,11 +13,17 @@ pub fn edit_cmd(args: SetCmd, gen_cmds: &ArchivedGeneratedCommands) -> Result<()
         permissions,
         scan_dir_depth,
         query_which,
+        detach,
+        env,
+        rm_env,
+        cwd,
         ext_filter,
         file_type_filter,
         encoder,
         args,
         append_args,
+        tag,
+        untag,
     } = args;
 
     if !gen_cmds.contains_key(&target) {
@@ -70,6 +80,18 @@ pub fn edit_cmd(args: SetCmd, gen_cmds: &ArchivedGeneratedCommands) -> Result<()
             if let Some(query_which) = query_which {
                 command.query_which = query_which;
             }
+            if let Some(detach) = detach {
+                command.detach = detach;
+            }
+            if !env.is_empty() || !rm_env.is_empty() {
+                let mut vars = command.env.take().unwrap_or_default();
+                vars.extend(env);
+                vars.retain(|key, _| !rm_env.contains(key));
+                command.env = Some(vars).filter(|vars| !vars.is_empty());
+            }
+            if let Some(cwd) = cwd {
+                command.cwd = Some(cwd).filter(|cwd| !cwd.is_empty());
+            }
             if let Some(mut new_ext_filter) = ext_filter {
                 match &mut command.filter {
                     FiltersKind::None => command.filter = FiltersKind::One(new_ext_filter),
@@ -151,9 +173,18 @@ pub fn edit_cmd(args: SetCmd, gen_cmds: &ArchivedGeneratedCommands) -> Result<()
                 }
             }
 
+            if !tag.is_empty() || !untag.is_empty() {
+                let mut tags = command.tags.take().unwrap_or_default();
+                tags.extend(tag);
+                tags.retain(|tag| !untag.contains(tag));
+                tags.sort();
+                tags.dedup();
+                command.tags = Some(tags).filter(|tags| !tags.is_empty());
+            }
+
             Some(command)
         });
-        overwrite_cmds(gen_cmds)?;
+        overwrite_journaled(cfg, Op::Set, gen_cmds)?;
     }
     Ok(())
 }
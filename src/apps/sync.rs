@@ -0,0 +1,385 @@
+use crate::{
+    cli::types::SyncCmd,
+    config::{
+        journal::{apply, Op},
+        source::{compile, fields, from_fields, render, show_value, snapshot, SourceCommand},
+        write::overwrite_journaled,
+    },
+    prelude::*,
+    utils::fs::atomic::write_atomic,
+};
+use rkyv::{de::deserializers::AllocDeserializer, Deserialize};
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fs::{create_dir_all, read_to_string},
+    hash::Hasher,
+    path::{Path, PathBuf},
+};
+
+/// the shared command set, kept as commands.toml so it can be reviewed and edited by hand
+const SHARED_FILE: &str = "commands.toml";
+
+/// which version wins a conflict
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Side {
+    Local,
+    Shared,
+}
+
+/// a field both sides changed since the last sync, `None` meaning unset (or the command
+/// being removed for the `command` field)
+#[derive(Debug, PartialEq)]
+struct FieldConflict {
+    field: String,
+    base: Option<serde_json::Value>,
+    local: Option<serde_json::Value>,
+    shared: Option<serde_json::Value>,
+}
+
+#[derive(Debug, PartialEq)]
+enum Outcome {
+    /// the shared version replaces the local one
+    Pulled(Option<SourceCommand>),
+    /// the local version replaces the shared one
+    Pushed(Option<SourceCommand>),
+    /// both sides changed different fields, or conflicts were settled by `--prefer`
+    Merged(Option<SourceCommand>, Vec<FieldConflict>),
+    Conflict(Vec<FieldConflict>),
+}
+
+fn merge_fields(
+    base: Option<&SourceCommand>,
+    local: &SourceCommand,
+    shared: &SourceCommand,
+    prefer: Option<Side>,
+) -> Result<Outcome> {
+    let base = base.map(fields).unwrap_or_default();
+    let (local, shared) = (fields(local), fields(shared));
+    let names: BTreeSet<&String> = base
+        .keys()
+        .chain(local.keys())
+        .chain(shared.keys())
+        .collect();
+
+    let mut merged = BTreeMap::new();
+    let mut conflicts = vec![];
+    for name in names {
+        let (base, local, shared) = (base.get(name), local.get(name), shared.get(name));
+        let value = if local == shared || shared == base {
+            local
+        } else if local == base {
+            shared
+        } else {
+            conflicts.push(FieldConflict {
+                field: name.clone(),
+                base: base.cloned(),
+                local: local.cloned(),
+                shared: shared.cloned(),
+            });
+            match prefer {
+                Some(Side::Local) => local,
+                Some(Side::Shared) => shared,
+                None => continue,
+            }
+        };
+        if let Some(value) = value {
+            merged.insert(name.clone(), value.clone());
+        }
+    }
+
+    if prefer.is_none() && !conflicts.is_empty() {
+        return Ok(Outcome::Conflict(conflicts));
+    }
+    Ok(Outcome::Merged(Some(from_fields(merged)?), conflicts))
+}
+
+/// `None` when both sides agree
+fn merge_key(
+    base: Option<SourceCommand>,
+    local: Option<SourceCommand>,
+    shared: Option<SourceCommand>,
+    prefer: Option<Side>,
+) -> Result<Option<Outcome>> {
+    Ok(Some(if local == shared {
+        return Ok(None);
+    } else if local == base {
+        Outcome::Pulled(shared)
+    } else if shared == base {
+        Outcome::Pushed(local)
+    } else {
+        match (&local, &shared) {
+            (Some(local), Some(shared)) => merge_fields(base.as_ref(), local, shared, prefer)?,
+            // removed on one side and changed on the other
+            _ => {
+                let json = |cmd: &Option<SourceCommand>| {
+                    cmd.as_ref().and_then(|cmd| serde_json::to_value(cmd).ok())
+                };
+                let conflict = FieldConflict {
+                    field: "command".into(),
+                    base: json(&base),
+                    local: json(&local),
+                    shared: json(&shared),
+                };
+                match prefer {
+                    Some(Side::Local) => Outcome::Merged(local, vec![conflict]),
+                    Some(Side::Shared) => Outcome::Merged(shared, vec![conflict]),
+                    None => Outcome::Conflict(vec![conflict]),
+                }
+            }
+        }
+    }))
+}
+
+fn merge(
+    base: &GeneratedCommands,
+    local: &GeneratedCommands,
+    shared: &GeneratedCommands,
+    prefer: Option<Side>,
+) -> Result<BTreeMap<String, Outcome>> {
+    let keys: BTreeSet<&String> = [base, local, shared]
+        .iter()
+        .flat_map(|cmds| cmds.commands.iter().flat_map(|cmds| cmds.keys()))
+        .collect();
+
+    let mut outcomes = BTreeMap::new();
+    for key in keys {
+        let (b, l, s) = (
+            snapshot(base, key),
+            snapshot(local, key),
+            snapshot(shared, key),
+        );
+        if let Some(outcome) = merge_key(b, l, s, prefer)? {
+            outcomes.insert(key.clone(), outcome);
+        }
+    }
+    Ok(outcomes)
+}
+
+/// the shared set as of this sync, except that conflicting keys keep their base so the next
+/// sync still sees both changes. Keys both sides already agreed on are included too, or
+/// removing one later would look like the other side adding it.
+fn next_base(
+    base: GeneratedCommands,
+    shared: &GeneratedCommands,
+    outcomes: &BTreeMap<String, Outcome>,
+) -> GeneratedCommands {
+    let keys: BTreeSet<String> = [&base, shared]
+        .iter()
+        .flat_map(|cmds| cmds.commands.iter().flat_map(|cmds| cmds.keys()))
+        .cloned()
+        .collect();
+
+    let mut new_base = base;
+    for key in keys {
+        if !matches!(outcomes.get(&key), Some(Outcome::Conflict(_))) {
+            apply(&mut new_base, &key, snapshot(shared, &key));
+        }
+    }
+    new_base
+}
+
+/// one base per shared directory, named after a hash of its path
+fn base_path(dir: &Path, cfg: &ConfigDir) -> PathBuf {
+    let mut hasher = seahash::SeaHasher::new();
+    hasher.write(dir.to_string_lossy().as_bytes());
+    ConfigPath::Sync
+        .abs(cfg)
+        .join(format!("{:016x}.toml", hasher.finish()))
+}
+
+/// commands.toml-style files that don't exist yet hold no commands
+fn read_cmds(path: &Path) -> Result<GeneratedCommands> {
+    match read_to_string(path) {
+        Ok(raw) => compile(&raw, path),
+        Err(e) if e.kind() == FileNotFound => Ok(GeneratedCommands::default()),
+        Err(e) => Err(anyhow!("Unable to read {}: {}", path.display(), e)),
+    }
+}
+
+fn print_conflicts(conflicts: &[FieldConflict]) {
+    for FieldConflict {
+        field,
+        base,
+        local,
+        shared,
+    } in conflicts
+    {
+        println!(
+            "    {}: base {}, local {}, shared {}",
+            field,
+            show_value(base),
+            show_value(local),
+            show_value(shared)
+        );
+    }
+}
+
+/// sets every key in `states` on `cmds`, warning about aliases claimed by another command
+fn apply_all(
+    cmds: &mut GeneratedCommands,
+    states: &[(&String, &Option<SourceCommand>)],
+    side: &str,
+) {
+    // cleared first so aliases moving between commands don't depend on the order
+    for (key, _) in states {
+        apply(cmds, key, None);
+    }
+    for (key, state) in states {
+        for alias in apply(cmds, key, (*state).clone()) {
+            eprintln!(
+                "alias `{}` of `{}` is taken in the {} set and was left out",
+                alias, key, side
+            );
+        }
+    }
+}
+
+pub fn sync_shared_dir(
+    SyncCmd {
+        dir,
+        prefer,
+        dry_run,
+    }: SyncCmd,
+    local_db: &ArchivedGeneratedCommands,
+    cfg: &ConfigDir,
+) -> Result<()> {
+    if !dir.is_dir() {
+        bail!("{} is not a directory", dir.display());
+    }
+    let dir = dir.canonicalize()?;
+    let shared_path = dir.join(SHARED_FILE);
+    let base_path = base_path(&dir, cfg);
+
+    let base = read_cmds(&base_path)?;
+    let mut shared = read_cmds(&shared_path)?;
+    let mut local = local_db.deserialize(&mut AllocDeserializer)?;
+    let outcomes = merge(&base, &local, &shared, prefer)?;
+
+    let (mut pull, mut push, mut conflicts) = (vec![], vec![], 0);
+    for (key, outcome) in &outcomes {
+        match outcome {
+            Outcome::Pulled(state) => {
+                println!("pulled    {}", key);
+                pull.push((key, state));
+            }
+            Outcome::Pushed(state) => {
+                println!("pushed    {}", key);
+                push.push((key, state));
+            }
+            Outcome::Merged(state, settled) => {
+                println!("merged    {}", key);
+                print_conflicts(settled);
+                pull.push((key, state));
+                push.push((key, state));
+            }
+            Outcome::Conflict(fields) => {
+                conflicts += 1;
+                println!("conflict  {}", key);
+                print_conflicts(fields);
+            }
+        }
+    }
+    let summary = format!(
+        "{} pulled, {} pushed, {} in conflict",
+        pull.len(),
+        push.len(),
+        conflicts
+    );
+    if dry_run {
+        println!("Dry run, nothing was synced: {}", summary);
+        return Ok(());
+    }
+
+    apply_all(&mut local, &pull, "local");
+    apply_all(&mut shared, &push, "shared");
+    let new_base = next_base(base, &shared, &outcomes);
+
+    // the base goes last, so a sync cut short leaves changes that are merged again next time
+    // rather than recorded as agreed before both sides have them
+    if !pull.is_empty() {
+        overwrite_journaled(cfg, Op::Sync, local)?;
+    }
+    write_atomic(&shared_path, render(&shared)?)?;
+    create_dir_all(ConfigPath::Sync.abs(cfg))?;
+    write_atomic(&base_path, render(&new_base)?)?;
+
+    println!("Synced with {}: {}", dir.display(), summary);
+    if conflicts > 0 {
+        println!("Settle conflicts by editing either side, or rerun with --prefer local|shared");
+    }
+    Ok(())
+}
+
+#[test]
+fn merges_per_field() {
+    let cmds = |entries: &[(&str, &str, &[&str])]| {
+        let mut commands = HashMap::new();
+        for (key, bin, args) in entries {
+            commands.insert(key.to_string(), GeneratedCommand::running(bin, args));
+        }
+        GeneratedCommands {
+            commands: Some(commands),
+            aliases: None,
+        }
+    };
+    let base = cmds(&[("ls", "ls", &[]), ("vim", "vim", &[]), ("rm", "rm", &[])]);
+    // local changes ls' args and removes rm, shared changes ls' bin and vim's args
+    let local = cmds(&[("ls", "ls", &["-l"]), ("vim", "vim", &["-u"])]);
+    let shared = cmds(&[
+        ("ls", "exa", &[]),
+        ("vim", "vim", &["-n"]),
+        ("rm", "rm", &[]),
+        ("git", "git", &[]),
+    ]);
+
+    let outcomes = merge(&base, &local, &shared, None).unwrap();
+    assert!(matches!(outcomes["git"], Outcome::Pulled(Some(_))));
+    assert_eq!(outcomes["rm"], Outcome::Pushed(None));
+    match &outcomes["ls"] {
+        Outcome::Merged(Some(merged), conflicts) => {
+            assert!(conflicts.is_empty());
+            assert_eq!(merged.bin(), "exa");
+            assert_eq!(fields(merged)["args"], serde_json::json!(["-l"]));
+        }
+        other => panic!("unexpected {:?}", other),
+    }
+    match &outcomes["vim"] {
+        Outcome::Conflict(conflicts) => {
+            assert_eq!(conflicts.len(), 1);
+            assert_eq!(conflicts[0].field, "args");
+        }
+        other => panic!("unexpected {:?}", other),
+    }
+
+    let preferred = merge(&base, &local, &shared, Some(Side::Shared)).unwrap();
+    match &preferred["vim"] {
+        Outcome::Merged(Some(merged), conflicts) => {
+            assert_eq!(conflicts.len(), 1);
+            assert_eq!(fields(merged)["args"], serde_json::json!(["-n"]));
+        }
+        other => panic!("unexpected {:?}", other),
+    }
+}
+
+#[test]
+fn agreed_keys_join_the_base() {
+    let ls = GeneratedCommands {
+        commands: Some(
+            vec![("ls".to_owned(), GeneratedCommand::running("ls", &[]))]
+                .into_iter()
+                .collect(),
+        ),
+        aliases: None,
+    };
+
+    // identical on both sides at the first sync
+    let outcomes = merge(&GeneratedCommands::default(), &ls, &ls, None).unwrap();
+    assert!(outcomes.is_empty());
+    let base = next_base(GeneratedCommands::default(), &ls, &outcomes);
+    assert!(base.get("ls").is_some());
+
+    // then removed locally, which has to reach the shared set rather than come back
+    let local = GeneratedCommands::default();
+    let outcomes = merge(&base, &local, &ls, None).unwrap();
+    assert_eq!(outcomes["ls"], Outcome::Pushed(None));
+}
@@ -1,32 +1,82 @@
 pub mod types;
 use types::*;
 
-use crate::{apps, prelude::*};
+use crate::{
+    apps,
+    config::{lock::DbLock, settings::Settings},
+    prelude::*,
+};
 
-pub fn exec_cli(app: Flurry) -> Result<()> {
+pub fn exec_cli(app: Flurry, cfg: &ConfigDir, settings: &Settings) -> Result<()> {
     if !app.interactive_mode && app.subcmd.is_none() {
         return Err(anyhow!("try `flurry help` for usage info"));
     }
 
     use SubCmds::*;
+    if let Some(Profile(args)) = app.subcmd {
+        return apps::profile::manage_profiles(args, settings, cfg);
+    }
+    if !ConfigPath::Commands.abs(cfg).exists() {
+        bail!(
+            "No profile named `{}`, create it with `flurry profile create {0}`",
+            cfg.profile_name()
+        );
+    }
     if let Some(Export(args)) = app.subcmd {
-        return apps::export::export_gen_cmds(args);
+        return apps::export::export_gen_cmds(args, cfg);
+    }
+    // restoring must not depend on the current database being readable
+    if let Some(Restore(args)) = app.subcmd {
+        return apps::restore::restore_backup(args, cfg);
+    }
+    if let Some(Diff(args)) = app.subcmd {
+        return apps::diff::diff_sources(args, cfg);
+    }
+    if let Some(History(args)) = app.subcmd {
+        return apps::history::show_history(args, cfg);
     }
+    // loading the database would migrate it before it can be checked
+    if let Some(Migrate(args)) = app.subcmd {
+        return apps::migrate::migrate_db(args, cfg);
+    }
+
+    // lookups only validate caches that changed since they were last validated
+    if !app.interactive_mode {
+        if let Some(Go(args)) = app.subcmd {
+            let cmds_db = CmdsDb::for_lookup(cfg)?;
+            return apps::go::dispatch_from_args(args, cmds_db.archive(), settings, cfg);
+        }
+        if let Some(Resolve(args)) = app.subcmd {
+            let cmds_db = CmdsDb::for_lookup(cfg)?;
+            return apps::go::dispatch_from_args(args.into(), cmds_db.archive(), settings, cfg);
+        }
+    }
+
+    // mutations hold the lock from read to write so concurrent invocations can't lose updates,
+    // the tui only locks when saving since it may stay open for a long time
+    let _lock = match app.subcmd {
+        Some(Add(_)) | Some(Import(_)) | Some(Rm(_)) | Some(Set(_)) | Some(Sync(_))
+        | Some(Undo(_)) => {
+            Some(DbLock::acquire(cfg)?)
+        }
+        _ => None,
+    };
 
-    let cmds_db = CmdsDb::from_cfg()?;
+    let cmds_db = CmdsDb::from_cfg(cfg)?;
     let cmds_rkyv = cmds_db.archive();
     if app.interactive_mode {
-        return apps::interactive::dispatch_interactive(cmds_rkyv);
+        return apps::interactive::dispatch_interactive(&cmds_db, settings, cfg);
     }
 
     match app.subcmd {
-        Some(Add(args)) => apps::add::insert_new_cmd(args, cmds_rkyv)?,
-        Some(Import(args)) => apps::import::import_cmds_from_file(args, cmds_rkyv)?,
-        Some(Go(args)) => apps::go::dispatch_from_args(args, cmds_rkyv)?,
+        Some(Add(args)) => apps::add::insert_new_cmd(args, cmds_rkyv, &settings.defaults, cfg)?,
+        Some(Import(args)) => apps::import::import_cmds_from_file(args, cmds_rkyv, cfg)?,
         Some(List(args)) => apps::list::list_cmds(args, cmds_rkyv),
-        Some(Rm(args)) => apps::rm::try_rm_cmd(args, cmds_rkyv)?,
-        Some(Set(args)) => apps::set_attr::edit_cmd(args, cmds_rkyv)?,
-        Some(Tui(_)) => apps::interactive::dispatch_interactive(cmds_rkyv)?,
+        Some(Rm(args)) => apps::rm::try_rm_cmd(args, cmds_rkyv, cfg)?,
+        Some(Set(args)) => apps::set_attr::edit_cmd(args, cmds_rkyv, cfg)?,
+        Some(Sync(args)) => apps::sync::sync_shared_dir(args, cmds_rkyv, cfg)?,
+        Some(Tui(_)) => apps::interactive::dispatch_interactive(&cmds_db, settings, cfg)?,
+        Some(Undo(args)) => apps::history::undo_changes(args, cmds_rkyv, cfg)?,
         _ => {}
     }
     Ok(())
@@ -1,4 +1,9 @@
-use crate::{prelude::*, utils::parse::*};
+use crate::{
+    apps::{import::OnConflict, sync::Side},
+    config::{format::Format, shell::Shell},
+    prelude::*,
+    utils::parse::*,
+};
 use argh::FromArgs;
 
 #[derive(FromArgs)]
@@ -6,6 +11,17 @@ use argh::FromArgs;
 pub struct Flurry {
     #[argh(switch, short = 'i', description = "enter interactive mode")]
     pub interactive_mode: bool,
+    #[argh(
+        option,
+        description = "config directory holding the command database (also $FLURRY_CONFIG)"
+    )]
+    pub db: Option<PathBuf>,
+    #[argh(
+        option,
+        description = "command set to use instead of the active profile",
+        from_str_fn(profile_from_arg)
+    )]
+    pub profile: Option<String>,
     #[argh(subcommand)]
     pub subcmd: Option<SubCmds>,
 }
@@ -14,13 +30,21 @@ pub struct Flurry {
 #[argh(subcommand)]
 pub enum SubCmds {
     Add(AddCmd),
+    Diff(DiffCmd),
     Export(ExportCmd),
     Go(GoCmd),
+    History(HistoryCmd),
     Import(ImportCmd),
     List(ListCmd),
+    Migrate(MigrateCmd),
+    Profile(ProfileCmd),
+    Resolve(ResolveCmd),
+    Restore(RestoreCmd),
     Rm(RmCmd),
     Set(SetCmd),
+    Sync(SyncCmd),
     Tui(InteractiveMode),
+    Undo(UndoCmd),
 }
 
 #[derive(FromArgs, PartialEq)]
@@ -44,7 +68,7 @@ pub struct AddCmd {
     #[argh(
         option,
         short = 'e',
-        description = "(OPTIONAL) encode output\n   options: url, json",
+        description = "(OPTIONAL) encode the user args\n   options: url, json",
         from_str_fn(encoder_from_arg)
     )]
     pub encoder: Option<EncoderKind>,
@@ -66,6 +90,20 @@ pub struct AddCmd {
         description = "query the target system for the binary location (or alias) instead of executing the raw value of bin"
     )]
     pub query_which: bool,
+    #[argh(
+        switch,
+        short = 'd',
+        description = "don't wait for the command when it runs, for GUI apps"
+    )]
+    pub detach: bool,
+    #[argh(
+        option,
+        description = "set a variable for the command as KEY=value, or unset it with -KEY. May be repeated",
+        from_str_fn(env_from_arg)
+    )]
+    pub env: Vec<(String, Option<String>)>,
+    #[argh(option, description = "directory the command runs in, ~ for home")]
+    pub cwd: Option<String>,
     #[argh(
         option,
         short = 'f',
@@ -78,6 +116,13 @@ pub struct AddCmd {
         description = "[FLAG] apply a regex filter instead of unicode"
     )]
     pub regex: bool,
+    #[argh(
+        option,
+        short = 't',
+        description = "tag used to group commands, may be repeated",
+        from_str_fn(tag_from_arg)
+    )]
+    pub tags: Vec<String>,
     #[argh(positional, description = "command's target value")]
     pub args: Vec<String>,
 }
@@ -94,10 +139,49 @@ pub struct ExportCmd {
     #[argh(
         option,
         short = 'o',
-        default = "PathBuf::from(\"flurry_exports.db\")",
-        description = "output path"
+        description = "output path (defaults to flurry_exports.db, or stdout with --shell)"
+    )]
+    pub output_file: Option<PathBuf>,
+    #[argh(
+        option,
+        description = "toml, json or rkyv (defaults to the output file's extension, else rkyv)",
+        from_str_fn(format_from_arg)
+    )]
+    pub format: Option<Format>,
+    #[argh(
+        option,
+        description = "bash, zsh or fish script defining a function per command and alias",
+        from_str_fn(shell_from_arg)
+    )]
+    pub shell: Option<Shell>,
+    #[argh(
+        switch,
+        description = "encrypt the export with a passphrase, read from $FLURRY_PASSPHRASE or asked for"
     )]
-    pub output_file: PathBuf,
+    pub encrypt: bool,
+    #[argh(
+        option,
+        long = "match",
+        description = "export commands whose key matches a glob (* and ?), may be repeated"
+    )]
+    pub globs: Vec<String>,
+    #[argh(
+        option,
+        description = "export commands whose key matches a regex, may be repeated",
+        from_str_fn(regex_from_arg)
+    )]
+    pub regex: Vec<String>,
+    #[argh(
+        option,
+        description = "export commands carrying a tag, may be repeated",
+        from_str_fn(tag_from_arg)
+    )]
+    pub tag: Vec<String>,
+    #[argh(
+        positional,
+        description = "commands (or their aliases) to export, everything when nothing is selected"
+    )]
+    pub keys: Vec<String>,
 }
 
 #[derive(FromArgs, PartialEq)]
@@ -121,10 +205,96 @@ pub struct GoCmd {
         description = "randomize file order for utils with dir_scan enabled"
     )]
     pub random: bool,
+    #[argh(
+        switch,
+        short = 'd',
+        description = "return right away instead of waiting for the command, with its output discarded"
+    )]
+    pub detach: bool,
+    #[argh(
+        switch,
+        short = 'n',
+        description = "print what would run instead of running it"
+    )]
+    pub dry_run: bool,
+    #[argh(switch, description = "print the dry run as json")]
+    pub json: bool,
     #[argh(positional, description = "additional args for command")]
     pub args: Vec<String>,
 }
 
+#[derive(FromArgs, PartialEq)]
+#[argh(
+    subcommand,
+    name = "resolve",
+    description = "Show what `go` would run, same as `go --dry-run`"
+)]
+pub struct ResolveCmd {
+    #[argh(positional, description = "command key")]
+    pub command: String,
+    #[argh(
+        switch,
+        description = "randomize file order for utils with dir_scan enabled"
+    )]
+    pub random: bool,
+    #[argh(switch, description = "print as json")]
+    pub json: bool,
+    #[argh(positional, description = "additional args for command")]
+    pub args: Vec<String>,
+}
+
+impl From<ResolveCmd> for GoCmd {
+    fn from(
+        ResolveCmd {
+            command,
+            random,
+            json,
+            args,
+        }: ResolveCmd,
+    ) -> Self {
+        GoCmd {
+            command,
+            random,
+            detach: false,
+            dry_run: true,
+            json,
+            args,
+        }
+    }
+}
+
+#[derive(FromArgs, PartialEq)]
+#[argh(
+    subcommand,
+    name = "diff",
+    description = "Compare the commands of two databases or export files"
+)]
+pub struct DiffCmd {
+    #[argh(
+        positional,
+        description = "a file, `current` for the active database or `backup:N` for a backup listed by `restore --list`. A single source is compared with the current database"
+    )]
+    pub sources: Vec<String>,
+    #[argh(switch, description = "print the changes as json")]
+    pub json: bool,
+}
+
+#[derive(FromArgs, PartialEq)]
+#[argh(
+    subcommand,
+    name = "history",
+    description = "Show recent changes to commands, newest first"
+)]
+pub struct HistoryCmd {
+    #[argh(
+        option,
+        short = 'n',
+        default = "20",
+        description = "number of changes to show"
+    )]
+    pub count: usize,
+}
+
 #[derive(FromArgs, PartialEq)]
 #[argh(
     subcommand,
@@ -133,13 +303,177 @@ pub struct GoCmd {
 )]
 pub struct ImportCmd {
     #[argh(option, short = 'f', description = "import file path")]
-    pub file_path: PathBuf,
+    pub file_path: Option<PathBuf>,
+    #[argh(
+        option,
+        description = "shell startup file whose aliases are imported as commands"
+    )]
+    pub from_shell: Option<PathBuf>,
+    #[argh(
+        option,
+        description = "alias syntax of --from-shell: bash, zsh or fish (detected from the file name when omitted)",
+        from_str_fn(shell_from_arg)
+    )]
+    pub shell: Option<Shell>,
+    #[argh(
+        switch,
+        description = "import applications installed with a .desktop file"
+    )]
+    pub desktop: bool,
+    #[argh(
+        option,
+        description = "application to import with --desktop, by key, desktop file id or name, may be repeated (picked from a list when omitted)"
+    )]
+    pub app: Vec<String>,
+    #[argh(
+        option,
+        description = "toml, json or rkyv (detected from the file when omitted), encrypted bundles are detected and decrypted",
+        from_str_fn(format_from_arg)
+    )]
+    pub format: Option<Format>,
+    #[argh(
+        option,
+        default = "OnConflict::Prompt",
+        description = "for keys that already exist: skip, overwrite, rename-suffix, prompt or fail (default prompt)",
+        from_str_fn(on_conflict_from_arg)
+    )]
+    pub on_conflict: OnConflict,
+    #[argh(
+        switch,
+        description = "print the planned adds, overwrites and renames without importing or prompting"
+    )]
+    pub dry_run: bool,
+}
+
+#[derive(FromArgs, PartialEq)]
+#[argh(
+    subcommand,
+    name = "sync",
+    description = "Merge commands with a shared directory, both ways"
+)]
+pub struct SyncCmd {
+    #[argh(positional, description = "directory holding the shared commands.toml")]
+    pub dir: PathBuf,
+    #[argh(
+        option,
+        description = "side whose changes win conflicts: local or shared (conflicts are only reported when omitted)",
+        from_str_fn(side_from_arg)
+    )]
+    pub prefer: Option<Side>,
+    #[argh(
+        switch,
+        description = "print what would be pulled, pushed and merged without syncing"
+    )]
+    pub dry_run: bool,
 }
 
 #[derive(FromArgs, PartialEq)]
 #[argh(subcommand, name = "tui", description = "Enter interactive mode")]
 pub struct InteractiveMode {}
 
+#[derive(FromArgs, PartialEq)]
+#[argh(
+    subcommand,
+    name = "undo",
+    description = "Revert the most recent changes to commands"
+)]
+pub struct UndoCmd {
+    #[argh(positional, default = "1", description = "number of changes to revert")]
+    pub count: usize,
+}
+
+#[derive(FromArgs, PartialEq)]
+#[argh(
+    subcommand,
+    name = "migrate",
+    description = "Upgrade a database or export file to the current format"
+)]
+pub struct MigrateCmd {
+    #[argh(
+        switch,
+        short = 'c',
+        description = "only report the format version, do not rewrite anything"
+    )]
+    pub check: bool,
+    #[argh(
+        option,
+        short = 'f',
+        description = "file to migrate instead of the command database"
+    )]
+    pub file_path: Option<PathBuf>,
+}
+
+#[derive(FromArgs, PartialEq)]
+#[argh(
+    subcommand,
+    name = "profile",
+    description = "Manage named command sets"
+)]
+pub struct ProfileCmd {
+    #[argh(subcommand)]
+    pub action: ProfileAction,
+}
+
+#[derive(FromArgs, PartialEq)]
+#[argh(subcommand)]
+pub enum ProfileAction {
+    Create(ProfileCreateCmd),
+    List(ProfileListCmd),
+    Use(ProfileUseCmd),
+}
+
+#[derive(FromArgs, PartialEq)]
+#[argh(subcommand, name = "create", description = "Create an empty profile")]
+pub struct ProfileCreateCmd {
+    #[argh(
+        positional,
+        description = "profile name",
+        from_str_fn(profile_from_arg)
+    )]
+    pub name: String,
+    #[argh(switch, short = 'u', description = "make it the active profile")]
+    pub activate: bool,
+}
+
+#[derive(FromArgs, PartialEq)]
+#[argh(
+    subcommand,
+    name = "list",
+    description = "List profiles, marking the active one"
+)]
+pub struct ProfileListCmd {}
+
+#[derive(FromArgs, PartialEq)]
+#[argh(
+    subcommand,
+    name = "use",
+    description = "Set the profile used when --profile isn't given"
+)]
+pub struct ProfileUseCmd {
+    #[argh(
+        positional,
+        description = "profile name",
+        from_str_fn(profile_from_arg)
+    )]
+    pub name: String,
+}
+
+#[derive(FromArgs, PartialEq)]
+#[argh(
+    subcommand,
+    name = "restore",
+    description = "Roll the database back to a backup"
+)]
+pub struct RestoreCmd {
+    #[argh(switch, short = 'l', description = "list backups without restoring")]
+    pub list: bool,
+    #[argh(
+        positional,
+        description = "backup number as shown by --list (1 is the newest)"
+    )]
+    pub index: Option<usize>,
+}
+
 #[derive(FromArgs, PartialEq)]
 #[argh(subcommand, name = "rm", description = "Remove a generated command")]
 pub struct RmCmd {
@@ -184,6 +518,27 @@ pub struct SetCmd {
         description = "query the target system for the binary location (or alias) instead of executing the raw value of bin"
     )]
     pub query_which: Option<bool>,
+    #[argh(
+        option,
+        description = "don't wait for the command when it runs, for GUI apps"
+    )]
+    pub detach: Option<bool>,
+    #[argh(
+        option,
+        description = "set a variable for the command as KEY=value, or unset it with -KEY. May be repeated",
+        from_str_fn(env_from_arg)
+    )]
+    pub env: Vec<(String, Option<String>)>,
+    #[argh(
+        option,
+        description = "stop setting or unsetting a variable, may be repeated"
+    )]
+    pub rm_env: Vec<String>,
+    #[argh(
+        option,
+        description = "directory the command runs in, ~ for home. Empty to run where flurry is called"
+    )]
+    pub cwd: Option<String>,
     #[argh(
         option,
         short = 'x',
@@ -201,7 +556,7 @@ pub struct SetCmd {
     #[argh(
         option,
         short = 'e',
-        description = "add file type filter (dirs only, files only) for recursive directory scans",
+        description = "encode the user args\n   options: url, json, none",
         from_str_fn(encoder_from_arg)
     )]
     pub encoder: Option<EncoderKind>,
@@ -218,4 +573,16 @@ pub struct SetCmd {
         description = "append arguments instead of replacing them"
     )]
     pub append_args: bool,
+    #[argh(
+        option,
+        description = "add a tag, may be repeated",
+        from_str_fn(tag_from_arg)
+    )]
+    pub tag: Vec<String>,
+    #[argh(
+        option,
+        description = "remove a tag, may be repeated",
+        from_str_fn(tag_from_arg)
+    )]
+    pub untag: Vec<String>,
 }
@@ -0,0 +1,64 @@
+use crate::{prelude::*, utils::time::now_millis};
+use std::{
+    cmp::Reverse,
+    fs::{copy, create_dir_all, read_dir, remove_file},
+    path::PathBuf,
+};
+
+pub const MAX_BACKUPS: usize = 10;
+
+const PREFIX: &str = "commands-";
+const EXT: &str = ".db";
+
+pub struct Backup {
+    pub path: PathBuf,
+    pub created: u64,
+}
+
+/// lists backups, newest first
+pub fn list_backups(cfg: &ConfigDir) -> Result<Vec<Backup>> {
+    let dir = ConfigPath::Backups.abs(cfg);
+    if !dir.exists() {
+        return Ok(vec![]);
+    }
+
+    let mut backups = vec![];
+    for entry in read_dir(dir)? {
+        let path = entry?.path();
+        let created = path
+            .file_name()
+            .and_then(|name| name.to_str())
+            .and_then(|name| name.strip_prefix(PREFIX))
+            .and_then(|name| name.strip_suffix(EXT))
+            .and_then(|stamp| stamp.parse().ok());
+
+        if let Some(created) = created {
+            backups.push(Backup { path, created });
+        }
+    }
+    backups.sort_by_key(|backup| Reverse(backup.created));
+    Ok(backups)
+}
+
+/// copies the current database into the backups dir, dropping the oldest past MAX_BACKUPS
+pub fn snapshot_current(cfg: &ConfigDir) -> Result<()> {
+    let current = ConfigPath::Cache.abs(cfg);
+    if !current.exists() {
+        return Ok(());
+    }
+
+    let dir = ConfigPath::Backups.abs(cfg);
+    create_dir_all(&dir)?;
+    let mut stamp = now_millis();
+    let mut target = dir.join(format!("{}{}{}", PREFIX, stamp, EXT));
+    while target.exists() {
+        stamp += 1;
+        target = dir.join(format!("{}{}{}", PREFIX, stamp, EXT));
+    }
+    copy(current, target)?;
+
+    for stale in list_backups(cfg)?.iter().skip(MAX_BACKUPS) {
+        remove_file(&stale.path)?;
+    }
+    Ok(())
+}
@@ -0,0 +1,161 @@
+//! passphrase encrypted export bundles. A bundle starts with a header holding its magic, the
+//! argon2id parameters, salt and nonce, followed by the export sealed with ChaCha20-Poly1305.
+//! The header is authenticated along with the export, so any change to either one is reported
+//! the same way as a wrong passphrase.
+
+use super::error::DbError;
+use crate::prelude::*;
+use argon2::{Algorithm, Argon2, Params, Version};
+use chacha20poly1305::{
+    aead::{Aead, KeyInit, Payload},
+    ChaCha20Poly1305, Key, Nonce,
+};
+use rand::RngCore;
+use std::{convert::TryInto, env::var};
+
+pub const PASSPHRASE_ENV: &str = "FLURRY_PASSPHRASE";
+
+const MAGIC: &[u8; 8] = b"FLRYENC\0";
+const VERSION: u8 = 1;
+const SALT_LEN: usize = 16;
+const NONCE_LEN: usize = 12;
+/// KiB, keeps a crafted header from asking for more memory than any sane export needs
+const MAX_M_COST: u32 = 1 << 20;
+/// iterations and lanes, bounded for the same reason since time grows with both
+const MAX_T_COST: u32 = 16;
+const MAX_P_COST: u32 = 16;
+/// magic, version, memory cost, iterations, parallelism, salt and nonce
+const HEADER_LEN: usize = MAGIC.len() + 1 + 4 * 3 + SALT_LEN + NONCE_LEN;
+
+pub fn is_sealed(bytes: &[u8]) -> bool {
+    bytes.starts_with(MAGIC)
+}
+
+/// $FLURRY_PASSPHRASE, else asked for on the terminal, twice when `confirm` is set
+pub fn passphrase(confirm: bool) -> Result<String> {
+    if let Some(passphrase) = var(PASSPHRASE_ENV).ok().filter(|pass| !pass.is_empty()) {
+        return Ok(passphrase);
+    }
+    let ask = |prompt: &str| {
+        rpassword::prompt_password(prompt).map_err(|e| {
+            anyhow!(
+                "Unable to ask for the passphrase ({}), set ${} instead",
+                e,
+                PASSPHRASE_ENV
+            )
+        })
+    };
+    let passphrase = ask("Passphrase: ")?;
+    if passphrase.is_empty() {
+        bail!("The passphrase can't be empty");
+    }
+    if confirm && ask("Repeat the passphrase: ")? != passphrase {
+        bail!("Passphrases don't match");
+    }
+    Ok(passphrase)
+}
+
+fn derive_key(passphrase: &str, salt: &[u8], params: Params) -> Result<Key> {
+    let mut key = Key::default();
+    Argon2::new(Algorithm::Argon2id, Version::V0x13, params)
+        .hash_password_into(passphrase.as_bytes(), salt, &mut key)
+        .map_err(|e| anyhow!("Unable to derive the key: {}", e))?;
+    Ok(key)
+}
+
+pub fn seal(export: &[u8], passphrase: &str) -> Result<Vec<u8>> {
+    let params = Params::default();
+    let mut salt = [0; SALT_LEN];
+    let mut nonce = [0; NONCE_LEN];
+    rand::thread_rng().fill_bytes(&mut salt);
+    rand::thread_rng().fill_bytes(&mut nonce);
+
+    let mut bundle = Vec::with_capacity(HEADER_LEN + export.len() + 16);
+    bundle.extend_from_slice(MAGIC);
+    bundle.push(VERSION);
+    for cost in [params.m_cost(), params.t_cost(), params.p_cost()].iter() {
+        bundle.extend_from_slice(&cost.to_le_bytes());
+    }
+    bundle.extend_from_slice(&salt);
+    bundle.extend_from_slice(&nonce);
+
+    let key = derive_key(passphrase, &salt, params)?;
+    let sealed = ChaCha20Poly1305::new(&key)
+        .encrypt(
+            Nonce::from_slice(&nonce),
+            Payload {
+                msg: export,
+                aad: &bundle,
+            },
+        )
+        .map_err(|_| anyhow!("Unable to encrypt the export"))?;
+    bundle.extend_from_slice(&sealed);
+    Ok(bundle)
+}
+
+/// the export inside a bundle, only ever returned once it's authenticated
+pub fn open(bundle: &[u8], passphrase: &str) -> Result<Vec<u8>> {
+    if bundle.len() < HEADER_LEN || !is_sealed(bundle) {
+        return Err(DbError::Corrupted("encrypted bundle is truncated".into()).into());
+    }
+    let (header, sealed) = bundle.split_at(HEADER_LEN);
+    if header[MAGIC.len()] != VERSION {
+        return Err(DbError::UnsupportedVersion(header[MAGIC.len()].into()).into());
+    }
+
+    let cost = |n: usize| {
+        let start = MAGIC.len() + 1 + 4 * n;
+        u32::from_le_bytes(header[start..start + 4].try_into().expect("4 bytes"))
+    };
+    if cost(0) > MAX_M_COST || cost(1) > MAX_T_COST || cost(2) > MAX_P_COST {
+        return Err(DbError::Corrupted("key parameters are out of range".into()).into());
+    }
+    let params = Params::new(cost(0), cost(1), cost(2), None)
+        .map_err(|e| DbError::Corrupted(format!("invalid key parameters, {}", e)))?;
+    let salt_start = HEADER_LEN - SALT_LEN - NONCE_LEN;
+    let salt = &header[salt_start..salt_start + SALT_LEN];
+    let nonce = &header[HEADER_LEN - NONCE_LEN..];
+
+    let key = derive_key(passphrase, salt, params)?;
+    ChaCha20Poly1305::new(&key)
+        .decrypt(
+            Nonce::from_slice(nonce),
+            Payload {
+                msg: sealed,
+                aad: header,
+            },
+        )
+        .map_err(|_| DbError::WrongPassphrase.into())
+}
+
+#[test]
+fn round_trips_and_rejects_wrong_passphrases() {
+    let bundle = seal(b"[commands.ls]\nbin = \"ls\"\n", "hunter2").unwrap();
+    assert!(is_sealed(&bundle));
+    assert_eq!(
+        open(&bundle, "hunter2").unwrap(),
+        b"[commands.ls]\nbin = \"ls\"\n"
+    );
+
+    let wrong = open(&bundle, "hunter3").unwrap_err();
+    assert!(matches!(
+        wrong.downcast_ref::<DbError>(),
+        Some(DbError::WrongPassphrase)
+    ));
+
+    let mut tampered = bundle.clone();
+    *tampered.last_mut().unwrap() ^= 1;
+    assert!(open(&tampered, "hunter2").is_err());
+    assert!(open(&bundle[..HEADER_LEN - 1], "hunter2").is_err());
+
+    // iterations are the second cost, rejected before any work is done
+    let mut costly = bundle;
+    let iterations = MAGIC.len() + 1 + 4;
+    costly[iterations..iterations + 4].copy_from_slice(&u32::MAX.to_le_bytes());
+    assert!(matches!(
+        open(&costly, "hunter2")
+            .unwrap_err()
+            .downcast_ref::<DbError>(),
+        Some(DbError::Corrupted(_))
+    ));
+}
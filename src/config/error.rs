@@ -0,0 +1,40 @@
+use std::{fmt, path::PathBuf};
+
+/// errors raised while loading a commands database from disk
+#[derive(Debug)]
+pub enum DbError {
+    Corrupted(String),
+    InvalidSource {
+        path: PathBuf,
+        line: usize,
+        col: usize,
+        reason: String,
+    },
+    UnsupportedVersion(u16),
+    WrongPassphrase,
+}
+
+impl fmt::Display for DbError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            DbError::Corrupted(reason) => write!(f, "database corrupted: {}", reason),
+            DbError::InvalidSource {
+                path,
+                line,
+                col,
+                reason,
+            } => write!(f, "{}:{}:{}: {}", path.display(), line, col, reason),
+            DbError::UnsupportedVersion(version) => write!(
+                f,
+                "database format version {} is not supported by this build of flurry",
+                version
+            ),
+            DbError::WrongPassphrase => write!(
+                f,
+                "unable to decrypt, the passphrase is wrong or the bundle was modified"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for DbError {}
@@ -0,0 +1,82 @@
+//! file formats commands are exported to and imported from. toml and json share the layout of
+//! commands.toml, rkyv is the database itself.
+
+use super::{
+    crypt,
+    source::{self, looks_like_archive},
+};
+use crate::prelude::*;
+use rkyv::{de::deserializers::AllocDeserializer, Deserialize};
+use std::{fs::read, path::Path, str::from_utf8};
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Format {
+    Json,
+    Rkyv,
+    Toml,
+}
+
+impl AsRef<str> for Format {
+    fn as_ref(&self) -> &str {
+        match self {
+            Format::Json => "json",
+            Format::Rkyv => "rkyv",
+            Format::Toml => "toml",
+        }
+    }
+}
+
+impl Format {
+    pub fn from_extension<P: AsRef<Path>>(path: P) -> Option<Self> {
+        match path.as_ref().extension()?.to_str()? {
+            "json" => Some(Format::Json),
+            "toml" => Some(Format::Toml),
+            "db" | "rkyv" => Some(Format::Rkyv),
+            _ => None,
+        }
+    }
+
+    /// by contents, for files named anything else and for the export inside a bundle
+    pub fn sniff(bytes: &[u8]) -> Self {
+        if looks_like_archive(bytes) {
+            Format::Rkyv
+        } else if bytes.iter().find(|b| !b.is_ascii_whitespace()) == Some(&b'{') {
+            Format::Json
+        } else {
+            Format::Toml
+        }
+    }
+}
+
+pub fn encode(db: &CmdsDb, format: Format) -> Result<Vec<u8>> {
+    let render = match format {
+        Format::Rkyv => return Ok(db.as_bytes().to_vec()),
+        Format::Json => source::render_json,
+        Format::Toml => source::render,
+    };
+    let cmds = db.archive().deserialize(&mut AllocDeserializer)?;
+    Ok(render(&cmds)?.into_bytes())
+}
+
+/// reads an export, decrypting bundles first. Without `format` it's taken from the extension,
+/// else from the contents.
+pub fn decode<P: AsRef<Path>>(path: P, format: Option<Format>) -> Result<CmdsDb> {
+    let path = path.as_ref();
+    let mut bytes = read(path)?;
+    let format = if crypt::is_sealed(&bytes) {
+        bytes = crypt::open(&bytes, &crypt::passphrase(false)?)?;
+        // the extension belongs to the bundle rather than the export inside it
+        format.unwrap_or_else(|| Format::sniff(&bytes))
+    } else {
+        format
+            .or_else(|| Format::from_extension(path))
+            .unwrap_or_else(|| Format::sniff(&bytes))
+    };
+
+    let compile = match format {
+        Format::Rkyv => return CmdsDb::from_bytes(&bytes),
+        Format::Json => source::compile_json,
+        Format::Toml => source::compile,
+    };
+    CmdsDb::from_cmds(&compile(from_utf8(&bytes)?, path)?)
+}
@@ -1,34 +1,322 @@
-use crate::prelude::*;
+use super::{
+    error::DbError,
+    header::DbHeader,
+    lock::DbLock,
+    migrate, source,
+    write::{overwrite_cmds, serialize_cmds, write_cache},
+};
+use crate::{prelude::*, utils::fs::atomic::write_atomic};
+use bytecheck::CheckBytes;
+use rkyv::{
+    de::deserializers::AllocDeserializer,
+    validation::{
+        check_archived_value_with_context, ArchiveBoundsContext, ArchiveBoundsError,
+        ArchiveBoundsValidator, ArchiveMemoryContext, ArchiveMemoryError, ArchiveValidator,
+    },
+    AlignedVec, Archive, Deserialize, Fallible,
+};
 use std::{
-    fs::{read, File},
+    alloc::Layout,
+    fs::{read, read_to_string, rename, File},
     io::Read,
+    path::Path,
+    time::UNIX_EPOCH,
 };
 
+/// rkyv 0.5's validator treats zero length claims (empty strings, vecs and maps)
+/// pointing into an already claimed value as overlaps, so those are skipped here
+pub(super) struct DbValidator(ArchiveValidator<ArchiveBoundsValidator>);
+
+impl DbValidator {
+    fn new(bytes: &[u8]) -> Self {
+        Self(ArchiveValidator::new(ArchiveBoundsValidator::new(bytes)))
+    }
+}
+
+impl Fallible for DbValidator {
+    type Error = ArchiveMemoryError<ArchiveBoundsError>;
+}
+
+impl ArchiveBoundsContext for DbValidator {
+    unsafe fn check_rel_ptr(
+        &mut self,
+        base: *const u8,
+        offset: isize,
+    ) -> Result<*const u8, Self::Error> {
+        self.0.check_rel_ptr(base, offset)
+    }
+
+    unsafe fn bounds_check_ptr(
+        &mut self,
+        ptr: *const u8,
+        layout: &Layout,
+    ) -> Result<(), Self::Error> {
+        self.0.bounds_check_ptr(ptr, layout)
+    }
+}
+
+impl ArchiveMemoryContext for DbValidator {
+    unsafe fn claim_bytes(&mut self, start: *const u8, len: usize) -> Result<(), Self::Error> {
+        if len == 0 {
+            return Ok(());
+        }
+        self.0.claim_bytes(start, len)
+    }
+}
+
+/// database bytes read into memory, aligned for the archive behind the header. Reading rather
+/// than mapping keeps files truncated or rewritten by something else from changing underneath.
+fn read_aligned(file: &mut File) -> Result<AlignedVec> {
+    let mut raw = vec![];
+    file.read_to_end(&mut raw)?;
+    let mut bytes = AlignedVec::new();
+    bytes.extend_from_slice(&raw);
+    Ok(bytes)
+}
+
+/// records that the cache with this checksum, length and mtime passed full validation, so
+/// lookups only have to check the checksum until the cache changes
+fn stamp(bytes: &[u8], file: &File) -> Option<String> {
+    let header = DbHeader::parse(bytes).ok()?;
+    let mtime = file.metadata().ok()?.modified().ok()?;
+    let nanos = mtime.duration_since(UNIX_EPOCH).ok()?.as_nanos();
+    Some(format!(
+        "{:016x} {} {}\n",
+        header.checksum, header.len, nanos
+    ))
+}
+
+/// databases written before the header existed keep their root position in .pos
+fn legacy_pos(cfg: &ConfigDir, bytes: &[u8]) -> Result<Option<usize>> {
+    if DbHeader::has_magic(bytes) || !ConfigPath::Pos.abs(cfg).exists() {
+        return Ok(None);
+    }
+    let pos_file = read(ConfigPath::Pos.abs(cfg))?;
+    Ok(Some(std::str::from_utf8(&pos_file)?.trim().parse()?))
+}
+
+pub(super) fn check_archive<T: Archive>(archive: &[u8], pos: usize) -> Result<&T::Archived>
+where
+    T::Archived: CheckBytes<DbValidator>,
+{
+    check_archived_value_with_context::<T, _>(archive, pos, &mut DbValidator::new(archive))
+        .map_err(|e| DbError::Corrupted(e.to_string()).into())
+}
+
+/// the root must fit in the archive and be aligned for it, the one thing about an archive that
+/// has to hold before its root can be read without full validation
+fn check_root(archive: &[u8], pos: usize) -> Result<(), DbError> {
+    use std::mem::{align_of, size_of};
+
+    match pos.checked_add(size_of::<ArchivedGeneratedCommands>()) {
+        Some(end) if end <= archive.len() => {}
+        _ => return Err(DbError::Corrupted("root position is out of bounds".into())),
+    }
+    // alignments are powers of two
+    let align = align_of::<ArchivedGeneratedCommands>();
+    if (archive.as_ptr() as usize + pos) & (align - 1) != 0 {
+        return Err(DbError::Corrupted("root position is misaligned".into()));
+    }
+    Ok(())
+}
+
 pub struct CmdsDb {
-    bytes: Vec<u8>,
+    bytes: AlignedVec,
+    start: usize,
     pos: usize,
 }
 
+enum Opened {
+    Current(CmdsDb),
+    Upgraded(GeneratedCommands),
+}
+
 impl CmdsDb {
-    pub fn from_cfg() -> Result<Self> {
-        let pos_file = read(ConfigPath::Pos.abs())?;
-        let pos: usize = std::str::from_utf8(&pos_file)?.parse()?;
+    /// loads and fully validates the cache, recompiling it first if commands.toml was edited since
+    pub fn from_cfg(cfg: &ConfigDir) -> Result<Self> {
+        Self::load(cfg, false)
+    }
+
+    /// loads the cache for a handful of lookups. Its checksum is always verified, the archive
+    /// itself only when it differs from the last one validated. `lookup_startup` below measures
+    /// the difference.
+    pub fn for_lookup(cfg: &ConfigDir) -> Result<Self> {
+        Self::load(cfg, true)
+    }
+
+    fn load(cfg: &ConfigDir, lookup: bool) -> Result<Self> {
+        if !source::cache_is_stale(cfg)? {
+            return Self::from_cache(cfg, lookup);
+        }
+
+        let _lock = DbLock::acquire(cfg)?;
+        if source::is_archive(cfg)? {
+            // validated where it is, so a damaged archive is never moved aside for an empty
+            // commands.toml to be compiled over it
+            let path = ConfigPath::Commands.abs(cfg);
+            let bytes = read_aligned(&mut File::open(&path)?)?;
+            let legacy_pos = legacy_pos(cfg, &bytes)?;
+            let cmds = match Self::open(bytes, legacy_pos, true)? {
+                Opened::Current(db) => db.archive().deserialize(&mut AllocDeserializer)?,
+                Opened::Upgraded(cmds) => cmds,
+            };
+            let db = Self::from_cmds(&cmds)?;
+            // moved to the cache first so the archive is the one backed up
+            rename(&path, ConfigPath::Cache.abs(cfg))?;
+            overwrite_cmds(cfg, cmds)?;
+            return Ok(db);
+        }
+
+        // another invocation may have compiled the cache while this one waited for the lock
+        if !source::cache_is_stale(cfg)? {
+            return Self::from_cache(cfg, lookup);
+        }
+        let path = ConfigPath::Commands.abs(cfg);
+        let db = Self::from_cmds(&source::compile(&read_to_string(&path)?, &path)?)?;
+        write_cache(cfg, db.as_bytes())?;
+        Ok(db)
+    }
+
+    fn from_cache(cfg: &ConfigDir, lookup: bool) -> Result<Self> {
+        let mut file = File::open(ConfigPath::Cache.abs(cfg))?;
+        let bytes = read_aligned(&mut file)?;
+        let stamp = stamp(&bytes, &file);
+        let validated = lookup
+            && stamp.is_some()
+            && read_to_string(ConfigPath::Validated.abs(cfg)).ok() == stamp;
+
+        let legacy_pos = legacy_pos(cfg, &bytes)?;
+        match Self::open(bytes, legacy_pos, !validated)? {
+            Opened::Current(db) => {
+                if let (false, Some(stamp)) = (validated, stamp) {
+                    // lookups still work without it, they just validate again next time
+                    let _ = write_atomic(ConfigPath::Validated.abs(cfg), stamp);
+                }
+                Ok(db)
+            }
+            Opened::Upgraded(cmds) => {
+                let db = Self::from_cmds(&cmds)?;
+                overwrite_cmds(cfg, cmds)?;
+                Ok(db)
+            }
+        }
+    }
+
+    /// a database anywhere on disk such as a backup, read into memory and validated
+    pub fn from_path<P: AsRef<Path>>(path: P) -> Result<Self> {
+        Self::from_bytes(&read(path)?)
+    }
+
+    /// a database read into memory, validated the same way as one on disk
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        let mut owned = AlignedVec::new();
+        owned.extend_from_slice(bytes);
+        match Self::open(owned, None, true)? {
+            Opened::Current(db) => Ok(db),
+            Opened::Upgraded(cmds) => Self::from_cmds(&cmds),
+        }
+    }
+
+    pub fn from_cmds(cmds: &GeneratedCommands) -> Result<Self> {
+        let mut bytes = AlignedVec::new();
+        bytes.extend_from_slice(&serialize_cmds(cmds)?);
+        let pos = DbHeader::parse(&bytes)?.pos as usize;
+        Ok(CmdsDb {
+            bytes,
+            start: DbHeader::SIZE,
+            pos,
+        })
+    }
+
+    /// `verify` checks the archive itself, the header's checksum is always checked
+    fn open(bytes: AlignedVec, legacy_pos: Option<usize>, verify: bool) -> Result<Opened> {
+        let (version, start, pos) = if DbHeader::has_magic(&bytes) {
+            let header = DbHeader::parse(&bytes)?;
+            header.verify(&bytes[DbHeader::SIZE..])?;
+            (header.version, DbHeader::SIZE, header.pos as usize)
+        } else {
+            let pos = match legacy_pos {
+                Some(pos) => pos,
+                None => migrate::legacy_root_pos(bytes.len())?,
+            };
+            (migrate::LEGACY, 0, pos)
+        };
 
-        let bytes = read(ConfigPath::Commands.abs())?;
-        Ok(CmdsDb { pos, bytes })
+        if version != migrate::CURRENT {
+            let cmds = migrate::upgrade(version, &bytes[start..], pos)?;
+            return Ok(Opened::Upgraded(cmds));
+        }
+        check_root(&bytes[start..], pos)?;
+        if verify {
+            check_archive::<GeneratedCommands>(&bytes[start..], pos)?;
+        }
+        Ok(Opened::Current(CmdsDb { bytes, start, pos }))
     }
 
-    pub fn from_path<P: AsRef<std::path::Path>>(path: P) -> Result<Self> {
-        let mut import_file = File::open(&path)?;
-        let mut buf = [0; 10];
-        import_file.read(&mut buf)?;
-        let pos: usize = std::str::from_utf8(&buf)?.parse()?;
+    /// identifies this exact version of the database, used to detect concurrent writes
+    pub fn fingerprint(&self) -> u64 {
+        seahash::hash(&self.bytes)
+    }
 
-        let bytes = read(path)?;
-        Ok(CmdsDb { pos, bytes })
+    /// the full file contents, header included
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.bytes
     }
 
     pub fn archive(&self) -> &ArchivedGeneratedCommands {
-        unsafe { rkyv::archived_value::<GeneratedCommands>(self.bytes.as_slice(), self.pos) }
+        // the root is bounds and alignment checked by open, and the rest validated by
+        // check_archive, now or when a cache with the same checksum was first loaded
+        unsafe { rkyv::archived_value::<GeneratedCommands>(&self.bytes[self.start..], self.pos) }
+    }
+}
+
+/// run with `cargo test --release lookup_startup -- --ignored --nocapture`
+#[test]
+#[ignore]
+fn lookup_startup() {
+    use std::{fmt::Write, time::Instant};
+
+    const COMMANDS: usize = 5000;
+    const RUNS: u32 = 50;
+
+    let mut raw = String::new();
+    for i in 0..COMMANDS {
+        let _ = writeln!(
+            raw,
+            "[commands.cmd{}]\nbin = \"echo\"\nargs = [\"{{@}}\", \"{}\"]",
+            i, i
+        );
+    }
+    let dir = std::env::temp_dir().join(format!("flurry-bench-{}", std::process::id()));
+    let cfg = ConfigDir::resolve(Some(dir.clone()));
+    std::fs::create_dir_all(ConfigPath::Data.abs(&cfg)).unwrap();
+    std::fs::write(ConfigPath::Commands.abs(&cfg), raw).unwrap();
+    CmdsDb::from_cfg(&cfg).unwrap();
+
+    let time = |open: &dyn Fn() -> CmdsDb| {
+        let start = Instant::now();
+        for _ in 0..RUNS {
+            assert!(open().archive().contains_key("cmd4999"));
+        }
+        start.elapsed() / RUNS
+    };
+    let validated = time(&|| CmdsDb::from_cfg(&cfg).unwrap());
+    let stamped = time(&|| CmdsDb::for_lookup(&cfg).unwrap());
+    std::fs::remove_dir_all(&dir).unwrap();
+
+    println!("{} commands, fully validated: {:?}", COMMANDS, validated);
+    println!("{} commands, checksum only: {:?}", COMMANDS, stamped);
+}
+
+#[test]
+fn rejects_roots_outside_the_archive() {
+    let mut archive = AlignedVec::new();
+    archive.extend_from_slice(&[0; 64]);
+    let size = std::mem::size_of::<ArchivedGeneratedCommands>();
+    assert!(check_root(&archive, 64 - size).is_ok());
+    assert!(check_root(&archive, 64 - size + 1).is_err());
+    assert!(check_root(&archive, usize::MAX).is_err());
+    if std::mem::align_of::<ArchivedGeneratedCommands>() > 1 {
+        assert!(check_root(&archive, 1).is_err());
     }
 }
@@ -0,0 +1,131 @@
+use super::error::DbError;
+use std::convert::TryInto;
+
+/// fixed size header prefixed to every database file
+///
+/// layout (little endian):
+/// | magic (4) | version (2) | reserved (2) | root pos (8) | payload len (8) | checksum (8) |
+#[derive(Debug, PartialEq)]
+pub struct DbHeader {
+    pub version: u16,
+    pub pos: u64,
+    pub len: u64,
+    pub checksum: u64,
+}
+
+impl DbHeader {
+    pub const MAGIC: &'static [u8; 4] = b"FLRY";
+    pub const VERSION: u16 = 4;
+    // multiple of rkyv's AlignedVec::ALIGNMENT so the payload stays aligned
+    pub const SIZE: usize = 32;
+
+    pub fn for_payload(payload: &[u8], pos: usize) -> Self {
+        Self {
+            version: Self::VERSION,
+            pos: pos as u64,
+            len: payload.len() as u64,
+            checksum: seahash::hash(payload),
+        }
+    }
+
+    pub fn has_magic(bytes: &[u8]) -> bool {
+        bytes.len() >= Self::MAGIC.len() && &bytes[..Self::MAGIC.len()] == Self::MAGIC
+    }
+
+    /// reads the version field without validating anything else
+    pub fn peek_version(bytes: &[u8]) -> Option<u16> {
+        if bytes.len() < 6 || !Self::has_magic(bytes) {
+            return None;
+        }
+        Some(u16::from_le_bytes([bytes[4], bytes[5]]))
+    }
+
+    pub fn to_bytes(&self) -> [u8; Self::SIZE] {
+        let mut buf = [0; Self::SIZE];
+        buf[0..4].copy_from_slice(Self::MAGIC);
+        buf[4..6].copy_from_slice(&self.version.to_le_bytes());
+        buf[8..16].copy_from_slice(&self.pos.to_le_bytes());
+        buf[16..24].copy_from_slice(&self.len.to_le_bytes());
+        buf[24..32].copy_from_slice(&self.checksum.to_le_bytes());
+        buf
+    }
+
+    pub fn parse(bytes: &[u8]) -> Result<Self, DbError> {
+        if bytes.len() < Self::SIZE {
+            return Err(DbError::Corrupted("file is smaller than its header".into()));
+        }
+        if !Self::has_magic(bytes) {
+            return Err(DbError::Corrupted("missing flurry magic number".into()));
+        }
+
+        let u64_at = |start: usize| u64::from_le_bytes(bytes[start..start + 8].try_into().unwrap());
+        let header = Self {
+            version: u16::from_le_bytes([bytes[4], bytes[5]]),
+            pos: u64_at(8),
+            len: u64_at(16),
+            checksum: u64_at(24),
+        };
+
+        // older versions are handed to config::migrate
+        if header.version == 0 || header.version > Self::VERSION {
+            return Err(DbError::UnsupportedVersion(header.version));
+        }
+        Ok(header)
+    }
+
+    /// catches truncated or padded files without reading the payload
+    pub fn check_bounds(&self, payload_len: usize) -> Result<(), DbError> {
+        if payload_len as u64 != self.len {
+            return Err(DbError::Corrupted(format!(
+                "expected {} bytes of commands but found {}",
+                self.len, payload_len
+            )));
+        }
+        if self.pos >= self.len {
+            return Err(DbError::Corrupted("root position is out of bounds".into()));
+        }
+        Ok(())
+    }
+
+    /// ensures the payload following the header is exactly what was written
+    pub fn verify(&self, payload: &[u8]) -> Result<(), DbError> {
+        self.check_bounds(payload.len())?;
+        if seahash::hash(payload) != self.checksum {
+            return Err(DbError::Corrupted("checksum mismatch".into()));
+        }
+        Ok(())
+    }
+}
+
+#[test]
+fn header_round_trip_and_corruption() {
+    let payload = b"0123456789abcdef".to_vec();
+    let header = DbHeader::for_payload(&payload, 8);
+    let bytes = header.to_bytes();
+
+    let parsed = DbHeader::parse(&bytes).unwrap();
+    assert_eq!(parsed, header);
+    assert!(parsed.verify(&payload).is_ok());
+
+    let mut tampered = payload.clone();
+    tampered[3] = b'X';
+    assert!(matches!(
+        parsed.verify(&tampered),
+        Err(DbError::Corrupted(_))
+    ));
+    assert!(matches!(
+        parsed.verify(&payload[..10]),
+        Err(DbError::Corrupted(_))
+    ));
+    assert!(matches!(
+        DbHeader::parse(&bytes[..20]),
+        Err(DbError::Corrupted(_))
+    ));
+
+    let mut future = bytes;
+    future[4] = 0xff;
+    assert!(matches!(
+        DbHeader::parse(&future),
+        Err(DbError::UnsupportedVersion(_))
+    ));
+}
@@ -0,0 +1,205 @@
+//! append-only log of every change made to a command through flurry, kept next to the
+//! database as journal.toml. Each command that writes the database records one change, an
+//! entry per command it touched, and changes are numbered in the order they were made.
+
+use super::source::{snapshot, SourceCommand};
+use crate::{prelude::*, utils::time::now_millis};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::BTreeSet,
+    fs::{read_to_string, OpenOptions},
+    io::Write,
+};
+
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Op {
+    Add,
+    Import,
+    Restore,
+    Rm,
+    Set,
+    Sync,
+    Tui,
+    Undo,
+}
+
+impl AsRef<str> for Op {
+    fn as_ref(&self) -> &str {
+        match self {
+            Op::Add => "add",
+            Op::Import => "import",
+            Op::Restore => "restore",
+            Op::Rm => "rm",
+            Op::Set => "set",
+            Op::Sync => "sync",
+            Op::Tui => "tui",
+            Op::Undo => "undo",
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+pub struct Entry {
+    /// number of the change this entry is part of, shared by every entry one command wrote
+    pub change: usize,
+    pub op: Op,
+    pub key: String,
+    pub at: u64,
+    /// number of the change an undo reverted
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub undoes: Option<usize>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub before: Option<SourceCommand>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub after: Option<SourceCommand>,
+}
+
+#[derive(Default, Deserialize, Serialize)]
+struct Journal {
+    #[serde(default)]
+    entry: Vec<Entry>,
+}
+
+/// one entry per command that differs between `old` and `new`, aliases included, all part of
+/// the same change
+pub fn diff(op: Op, old: &GeneratedCommands, new: &GeneratedCommands) -> Vec<Entry> {
+    let at = now_millis();
+    let keys: BTreeSet<&String> = old
+        .commands
+        .iter()
+        .chain(new.commands.iter())
+        .flat_map(|cmds| cmds.keys())
+        .collect();
+
+    keys.into_iter()
+        .filter_map(|key| {
+            let before = snapshot(old, key);
+            let after = snapshot(new, key);
+            if before == after {
+                return None;
+            }
+            Some(Entry {
+                change: 0,
+                op,
+                key: key.clone(),
+                at,
+                undoes: None,
+                before,
+                after,
+            })
+        })
+        .collect()
+}
+
+pub fn read_journal(cfg: &ConfigDir) -> Result<Vec<Entry>> {
+    let path = ConfigPath::Journal.abs(cfg);
+    let raw = match read_to_string(&path) {
+        Ok(raw) => raw,
+        Err(e) if e.kind() == FileNotFound => return Ok(vec![]),
+        Err(e) => return Err(e.into()),
+    };
+    let journal: Journal =
+        toml::from_str(&raw).map_err(|e| anyhow!("Invalid {}: {}", path.display(), e))?;
+    Ok(journal.entry)
+}
+
+/// groups entries by change, oldest first
+pub fn changes(entries: &[Entry]) -> Vec<&[Entry]> {
+    let mut changes = vec![];
+    let mut rest = entries;
+    while let Some(first) = rest.first() {
+        let len = rest
+            .iter()
+            .take_while(|entry| entry.change == first.change)
+            .count();
+        let (change, tail) = rest.split_at(len);
+        changes.push(change);
+        rest = tail;
+    }
+    changes
+}
+
+/// appends as `[[entry]]` tables so the file never has to be rewritten. `change` counts from 0
+/// within `entries` and is numbered after the changes already in the journal.
+pub fn append(cfg: &ConfigDir, mut entries: Vec<Entry>) -> Result<()> {
+    if entries.is_empty() {
+        return Ok(());
+    }
+    let next = read_journal(cfg)?.last().map_or(0, |entry| entry.change) + 1;
+    for entry in &mut entries {
+        entry.change += next;
+    }
+    let raw = toml::to_string(&Journal { entry: entries })?;
+    let mut journal = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(ConfigPath::Journal.abs(cfg))?;
+    journal.write_all(raw.as_bytes())?;
+    journal.sync_all()?;
+    Ok(())
+}
+
+/// sets `key` and the aliases pointing at it to `state`, returning aliases that couldn't be
+/// restored because something else claimed them since
+pub fn apply(cmds: &mut GeneratedCommands, key: &str, state: Option<SourceCommand>) -> Vec<String> {
+    let commands = cmds.commands.get_or_insert_with(HashMap::new);
+    let aliases = cmds.aliases.get_or_insert_with(HashMap::new);
+    commands.remove(key);
+    aliases.retain(|_, target| target != key);
+
+    let mut taken = vec![];
+    if let Some(state) = state {
+        for alias in state.aliases() {
+            if commands.contains_key(alias) || aliases.contains_key(alias) {
+                taken.push(alias.clone());
+            } else {
+                aliases.insert(alias.clone(), key.to_owned());
+            }
+        }
+        commands.insert(key.to_owned(), state.into_cmd());
+    }
+    taken
+}
+
+#[test]
+fn diff_and_apply_round_trip() {
+    let cmd = |bin: &str| GeneratedCommand::running(bin, &[]);
+
+    let mut commands = HashMap::new();
+    commands.insert("ls".to_owned(), cmd("ls"));
+    commands.insert(
+        "music".to_owned(),
+        GeneratedCommand {
+            aliases: Some(vec!["m".into()]),
+            ..cmd("mpv")
+        },
+    );
+    let mut aliases = HashMap::new();
+    aliases.insert("m".to_owned(), "music".to_owned());
+    let old = GeneratedCommands {
+        commands: Some(commands),
+        aliases: Some(aliases),
+    };
+
+    let mut new = GeneratedCommands::default();
+    new.commands = Some(HashMap::new());
+    new.commands
+        .as_mut()
+        .unwrap()
+        .insert("ls".to_owned(), cmd("exa"));
+
+    let entries = diff(Op::Set, &old, &new);
+    assert_eq!(entries.len(), 2);
+    assert!(entries.iter().all(|entry| entry.before.is_some()));
+    assert_eq!(changes(&entries).len(), 1);
+
+    // round trips through the on disk format
+    let raw = toml::to_string(&Journal { entry: entries }).unwrap();
+    let entries = toml::from_str::<Journal>(&raw).unwrap().entry;
+
+    for entry in entries.into_iter().rev() {
+        assert!(apply(&mut new, &entry.key, entry.before).is_empty());
+    }
+    assert_eq!(new, old);
+}
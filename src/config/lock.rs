@@ -0,0 +1,73 @@
+use crate::prelude::*;
+use std::{
+    fs::File,
+    sync::atomic::{AtomicBool, Ordering},
+};
+
+// flock is per open file, so a second acquire in the same process would deadlock
+static HELD: AtomicBool = AtomicBool::new(false);
+
+/// advisory lock on the config directory, held across a read-modify-write of the database
+pub struct DbLock {
+    dir: Option<File>,
+}
+
+impl DbLock {
+    pub fn acquire(cfg: &ConfigDir) -> Result<Self> {
+        if HELD.swap(true, Ordering::SeqCst) {
+            return Ok(Self { dir: None });
+        }
+
+        let dir = match File::open(ConfigPath::Base.abs(cfg)) {
+            Ok(dir) => dir,
+            Err(e) => {
+                HELD.store(false, Ordering::SeqCst);
+                return Err(e.into());
+            }
+        };
+        if let Err(e) = lock_exclusive(&dir) {
+            HELD.store(false, Ordering::SeqCst);
+            return Err(e.into());
+        }
+        Ok(Self { dir: Some(dir) })
+    }
+}
+
+impl Drop for DbLock {
+    fn drop(&mut self) {
+        if let Some(ref dir) = self.dir {
+            unlock(dir);
+            HELD.store(false, Ordering::SeqCst);
+        }
+    }
+}
+
+#[cfg(unix)]
+fn lock_exclusive(dir: &File) -> std::io::Result<()> {
+    use std::os::unix::io::AsRawFd;
+
+    let fd = dir.as_raw_fd();
+    if unsafe { libc::flock(fd, libc::LOCK_EX | libc::LOCK_NB) } == 0 {
+        return Ok(());
+    }
+    eprintln!("Waiting for another flurry process to finish writing...");
+    if unsafe { libc::flock(fd, libc::LOCK_EX) } == 0 {
+        Ok(())
+    } else {
+        Err(std::io::Error::last_os_error())
+    }
+}
+
+#[cfg(unix)]
+fn unlock(dir: &File) {
+    use std::os::unix::io::AsRawFd;
+    unsafe { libc::flock(dir.as_raw_fd(), libc::LOCK_UN) };
+}
+
+#[cfg(not(unix))]
+fn lock_exclusive(_: &File) -> std::io::Result<()> {
+    Ok(())
+}
+
+#[cfg(not(unix))]
+fn unlock(_: &File) {}
@@ -0,0 +1,99 @@
+//! schema versions of the commands database
+//!
+//! | version | layout                                                          |
+//! |---------|-----------------------------------------------------------------|
+//! | 0       | headerless archive, root in `.pos` or at the end of the archive |
+//! | 1       | `DbHeader` followed by `v1::GeneratedCommands`                  |
+//! | 2       | commands gained `tags`                                          |
+//! | 3       | commands gained `detach`                                        |
+//! | 4       | commands gained `env` and `cwd`                                 |
+//!
+//! Changing `GeneratedCommands` (or anything it archives) requires freezing the
+//! outgoing structs, along with copies of every enum they archive, in a `vN` module,
+//! bumping `DbHeader::VERSION` and adding a step to `upgrade` which converts the
+//! frozen structs into the new ones.
+
+mod v1;
+mod v2;
+mod v3;
+
+use super::{error::DbError, get::check_archive, header::DbHeader};
+use crate::prelude::*;
+use rkyv::{de::deserializers::AllocDeserializer, Deserialize};
+use std::path::Path;
+
+pub const CURRENT: u16 = DbHeader::VERSION;
+pub const LEGACY: u16 = 0;
+
+/// bare archives written without a .pos file end with their root
+pub fn legacy_root_pos(archive_len: usize) -> Result<usize> {
+    archive_len
+        .checked_sub(std::mem::size_of::<v1::ArchivedGeneratedCommands>())
+        .ok_or_else(|| DbError::Corrupted("file is too small to hold commands".into()).into())
+}
+
+/// rebuilds an archive written at `version` with the current layout
+pub fn upgrade(version: u16, archive: &[u8], pos: usize) -> Result<GeneratedCommands> {
+    match version {
+        // v0 only lacks the header, its archive is laid out like v1
+        LEGACY | 1 => Ok(check_archive::<v1::GeneratedCommands>(archive, pos)?
+            .deserialize(&mut AllocDeserializer)?
+            .into()),
+        2 => Ok(check_archive::<v2::GeneratedCommands>(archive, pos)?
+            .deserialize(&mut AllocDeserializer)?
+            .into()),
+        3 => Ok(check_archive::<v3::GeneratedCommands>(archive, pos)?
+            .deserialize(&mut AllocDeserializer)?
+            .into()),
+        unknown => Err(DbError::UnsupportedVersion(unknown).into()),
+    }
+}
+
+pub fn version_of<P: AsRef<Path>>(path: P) -> Result<u16> {
+    use std::{fs::File, io::Read};
+
+    let mut head = Vec::with_capacity(DbHeader::SIZE);
+    File::open(path)?
+        .take(DbHeader::SIZE as u64)
+        .read_to_end(&mut head)?;
+    Ok(DbHeader::peek_version(&head).unwrap_or(LEGACY))
+}
+
+#[test]
+fn upgrades_headerless_archive() {
+    use rkyv::{
+        ser::{serializers::WriteSerializer, Serializer},
+        AlignedVec,
+    };
+
+    let mut commands = HashMap::new();
+    commands.insert(
+        "ls".to_owned(),
+        v1::GeneratedCommand {
+            bin: "ls".into(),
+            dfl_args: None,
+            encoder: None,
+            aliases: None,
+            filter: v1::FiltersKind::None,
+            permissions: v1::PermissionsKind::Any,
+            query_which: false,
+            scan_dir: v1::ScanDirKind::None,
+        },
+    );
+    let legacy = v1::GeneratedCommands {
+        commands: Some(commands),
+        aliases: None,
+    };
+
+    let mut serializer = WriteSerializer::new(AlignedVec::new());
+    serializer.serialize_value(&legacy).unwrap();
+    let path = std::env::temp_dir().join(format!("flurry-legacy-{}.db", std::process::id()));
+    std::fs::write(&path, serializer.into_inner()).unwrap();
+
+    assert_eq!(version_of(&path).unwrap(), LEGACY);
+    let db = CmdsDb::from_path(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+
+    assert!(db.archive().contains_key("ls"));
+    assert_eq!(DbHeader::peek_version(db.as_bytes()), Some(CURRENT));
+}
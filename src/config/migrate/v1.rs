@@ -0,0 +1,174 @@
+//! layout of versions 0 and 1, before commands carried tags
+//!
+//! Every type it archives is copied here, the live ones in `types` are free to change.
+
+// old archives are only ever read, so rkyv's archived variants are never built
+#![allow(dead_code)]
+
+use crate::config::types;
+use bytecheck::CheckBytes;
+use rkyv::{Archive, Deserialize, Serialize};
+use std::collections::HashMap;
+
+#[derive(Archive, Deserialize, Serialize)]
+#[archive(derive(CheckBytes))]
+pub struct GeneratedCommands {
+    pub commands: Option<HashMap<String, GeneratedCommand>>,
+    pub aliases: Option<HashMap<String, String>>,
+}
+
+#[derive(Archive, Deserialize, Serialize)]
+#[archive(derive(CheckBytes))]
+pub struct GeneratedCommand {
+    pub bin: String,
+    pub dfl_args: Option<Vec<String>>,
+    pub encoder: Option<EncoderKind>,
+    pub aliases: Option<Vec<String>>,
+    pub filter: FiltersKind,
+    pub permissions: PermissionsKind,
+    pub query_which: bool,
+    pub scan_dir: ScanDirKind,
+}
+
+#[derive(Archive, Deserialize, Serialize)]
+#[archive(derive(CheckBytes))]
+pub enum EncoderKind {
+    Json,
+    Url,
+    None,
+}
+
+#[derive(Archive, Deserialize, Serialize)]
+#[archive(derive(CheckBytes))]
+pub enum PermissionsKind {
+    Any,
+    Group,
+    Root,
+    User,
+}
+
+#[derive(Archive, Deserialize, Serialize)]
+#[archive(derive(CheckBytes))]
+pub enum FileTypeFilter {
+    Dirs,
+    Files,
+}
+
+#[derive(Archive, Deserialize, Serialize)]
+#[archive(derive(CheckBytes))]
+pub enum FilterKind {
+    Exts(Vec<String>),
+    FileType(FileTypeFilter),
+    RegEx(String),
+    Raw(String),
+    None,
+}
+
+#[derive(Archive, Deserialize, Serialize)]
+#[archive(derive(CheckBytes))]
+pub enum FiltersKind {
+    One(FilterKind),
+    Many(Vec<FilterKind>),
+    None,
+}
+
+#[derive(Archive, Deserialize, Serialize)]
+#[archive(derive(CheckBytes))]
+pub enum ScanDirKind {
+    Depth(u8),
+    None,
+}
+
+impl From<GeneratedCommand> for types::GeneratedCommand {
+    fn from(cmd: GeneratedCommand) -> Self {
+        Self {
+            bin: cmd.bin,
+            dfl_args: cmd.dfl_args,
+            encoder: cmd.encoder.map(Into::into),
+            aliases: cmd.aliases,
+            filter: cmd.filter.into(),
+            permissions: cmd.permissions.into(),
+            query_which: cmd.query_which,
+            scan_dir: cmd.scan_dir.into(),
+            tags: None,
+            detach: false,
+            env: None,
+            cwd: None,
+        }
+    }
+}
+
+impl From<GeneratedCommands> for types::GeneratedCommands {
+    fn from(cmds: GeneratedCommands) -> Self {
+        Self {
+            commands: cmds.commands.map(|commands| {
+                commands
+                    .into_iter()
+                    .map(|(key, cmd)| (key, cmd.into()))
+                    .collect()
+            }),
+            aliases: cmds.aliases,
+        }
+    }
+}
+
+impl From<EncoderKind> for types::EncoderKind {
+    fn from(kind: EncoderKind) -> Self {
+        match kind {
+            EncoderKind::Json => Self::Json,
+            EncoderKind::Url => Self::Url,
+            EncoderKind::None => Self::None,
+        }
+    }
+}
+
+impl From<PermissionsKind> for types::PermissionsKind {
+    fn from(kind: PermissionsKind) -> Self {
+        match kind {
+            PermissionsKind::Any => Self::Any,
+            PermissionsKind::Group => Self::Group,
+            PermissionsKind::Root => Self::Root,
+            PermissionsKind::User => Self::User,
+        }
+    }
+}
+
+impl From<FileTypeFilter> for types::FileTypeFilter {
+    fn from(ty: FileTypeFilter) -> Self {
+        match ty {
+            FileTypeFilter::Dirs => Self::Dirs,
+            FileTypeFilter::Files => Self::Files,
+        }
+    }
+}
+
+impl From<FilterKind> for types::FilterKind {
+    fn from(kind: FilterKind) -> Self {
+        match kind {
+            FilterKind::Exts(exts) => Self::Exts(exts),
+            FilterKind::FileType(ty) => Self::FileType(ty.into()),
+            FilterKind::RegEx(pat) => Self::RegEx(pat),
+            FilterKind::Raw(pat) => Self::Raw(pat),
+            FilterKind::None => Self::None,
+        }
+    }
+}
+
+impl From<FiltersKind> for types::FiltersKind {
+    fn from(kind: FiltersKind) -> Self {
+        match kind {
+            FiltersKind::One(filter) => Self::One(filter.into()),
+            FiltersKind::Many(filters) => Self::Many(filters.into_iter().map(Into::into).collect()),
+            FiltersKind::None => Self::None,
+        }
+    }
+}
+
+impl From<ScanDirKind> for types::ScanDirKind {
+    fn from(kind: ScanDirKind) -> Self {
+        match kind {
+            ScanDirKind::Depth(depth) => Self::Depth(depth),
+            ScanDirKind::None => Self::None,
+        }
+    }
+}
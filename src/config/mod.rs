@@ -1,21 +1,126 @@
+pub mod backup;
+pub mod crypt;
+pub mod error;
+pub mod format;
 pub mod get;
+pub mod header;
+pub mod journal;
+pub mod lock;
+pub mod migrate;
+pub mod settings;
+pub mod shell;
+pub mod source;
 pub mod types;
 pub mod write;
 
+use std::{
+    env::var_os,
+    path::{Path, PathBuf},
+};
+
+/// root directory holding the settings, lock and command sets, plus the selected profile
+#[derive(Clone, Debug, PartialEq)]
+pub struct ConfigDir {
+    root: PathBuf,
+    profile: Option<String>,
+}
+
+impl ConfigDir {
+    pub const ENV: &'static str = "FLURRY_CONFIG";
+    pub const DEFAULT_PROFILE: &'static str = "default";
+
+    /// `--db` flag, then $FLURRY_CONFIG, then $XDG_CONFIG_HOME/flurry, then ~/.config/flurry
+    pub fn resolve(db_flag: Option<PathBuf>) -> Self {
+        if let Some(root) = db_flag {
+            return Self::at(root);
+        }
+        if let Some(root) = var_os(Self::ENV).filter(|root| !root.is_empty()) {
+            return Self::at(root);
+        }
+        Self::at(Self::default_root())
+    }
+
+    /// where the config lives when neither `--db` nor $FLURRY_CONFIG point elsewhere
+    fn default_root() -> PathBuf {
+        // the spec says relative values are invalid and should be ignored
+        if let Some(xdg) = var_os("XDG_CONFIG_HOME")
+            .map(PathBuf::from)
+            .filter(|xdg| xdg.is_absolute())
+        {
+            return xdg.join("flurry");
+        }
+        crate::utils::os::home().join(".config/flurry")
+    }
+
+    fn at<P: Into<PathBuf>>(root: P) -> Self {
+        Self {
+            root: root.into(),
+            profile: None,
+        }
+    }
+
+    /// switches to a named command set, `default` being the one at the root
+    pub fn with_profile(self, profile: Option<String>) -> Self {
+        Self {
+            profile: profile.filter(|name| name != Self::DEFAULT_PROFILE),
+            ..self
+        }
+    }
+
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// the root, unless it's the one flurry finds without `--db` or $FLURRY_CONFIG
+    pub fn custom_root(&self) -> Option<&Path> {
+        Some(self.root()).filter(|root| *root != Self::default_root())
+    }
+
+    pub fn profile(&self) -> Option<&str> {
+        self.profile.as_deref()
+    }
+
+    pub fn profile_name(&self) -> &str {
+        self.profile().unwrap_or(Self::DEFAULT_PROFILE)
+    }
+
+    /// directory holding the selected profile's database and backups
+    fn data_dir(&self) -> PathBuf {
+        match self.profile {
+            Some(ref name) => ConfigPath::Profiles.abs(self).join(name),
+            None => self.root.clone(),
+        }
+    }
+}
+
 pub enum ConfigPath {
+    Backups,
     Base,
+    Cache,
     Commands,
+    Data,
+    Journal,
     Pos,
+    Profiles,
+    Settings,
+    Sync,
+    Validated,
 }
 
 impl ConfigPath {
-    pub fn abs(&self) -> std::path::PathBuf {
-        let mut path = crate::utils::os::home();
-        path.push(match self {
-            ConfigPath::Base => ".config/flurry",
-            ConfigPath::Commands => ".config/flurry/commands.toml",
-            ConfigPath::Pos => ".config/flurry/.pos",
-        });
-        path
+    pub fn abs(&self, cfg: &ConfigDir) -> PathBuf {
+        match self {
+            ConfigPath::Backups => cfg.data_dir().join("backups"),
+            ConfigPath::Base => cfg.root().to_owned(),
+            ConfigPath::Cache => cfg.data_dir().join("commands.db"),
+            ConfigPath::Commands => cfg.data_dir().join("commands.toml"),
+            ConfigPath::Data => cfg.data_dir(),
+            ConfigPath::Journal => cfg.data_dir().join("journal.toml"),
+            ConfigPath::Pos => cfg.data_dir().join(".pos"),
+            ConfigPath::Profiles => cfg.root().join("profiles"),
+            ConfigPath::Settings => cfg.root().join("settings.toml"),
+            ConfigPath::Sync => cfg.data_dir().join("sync"),
+            ConfigPath::Validated => cfg.data_dir().join("commands.db.validated"),
+        }
     }
 }
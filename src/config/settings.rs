@@ -0,0 +1,189 @@
+//! settings.toml, e.g.
+//!
+//! ```toml
+//! profile_fallback = true
+//! confirm_run = false
+//!
+//! [defaults]
+//! scan_depth = 3
+//! permissions = "any"
+//! encoder = "url"
+//!
+//! [tui]
+//! poll_rate = 100
+//! header_color = "blue"
+//! selection_color = "#5fafff"
+//! rm_color = "light-red"
+//! ```
+
+use super::lock::DbLock;
+use crate::{
+    prelude::*,
+    tui::prelude::{Color, Style},
+    utils::{
+        fs::atomic::write_atomic,
+        parse::{color_from_arg, profile_from_arg},
+    },
+};
+use serde::{Deserialize, Serialize};
+use std::fs::read_to_string;
+
+/// user preferences kept in settings.toml at the config root, shared by every profile
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Settings {
+    /// profile used when `--profile` isn't given
+    pub profile: Option<String>,
+    /// look keys missing from the active profile up in the default set
+    pub profile_fallback: bool,
+    /// ask before `go` or the tui runs a command
+    pub confirm_run: bool,
+    pub defaults: CmdDefaults,
+    pub tui: TuiSettings,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            profile: None,
+            profile_fallback: true,
+            confirm_run: false,
+            defaults: CmdDefaults::default(),
+            tui: TuiSettings::default(),
+        }
+    }
+}
+
+/// attributes given to commands created by `add` or the tui when they aren't spelled out
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct CmdDefaults {
+    /// how deep `add -s` (or answering yes in the tui) scans, 0 disables scanning
+    pub scan_depth: u8,
+    /// permissions of commands added without `-p`, which always requires root, or left empty in
+    /// the tui
+    pub permissions: PermissionsKind,
+    /// encoder of commands added without `-e`, or left empty in the tui
+    pub encoder: EncoderKind,
+}
+
+impl Default for CmdDefaults {
+    fn default() -> Self {
+        Self {
+            scan_depth: u8::MAX,
+            permissions: PermissionsKind::Any,
+            encoder: EncoderKind::None,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct TuiSettings {
+    /// milliseconds between input polls
+    pub poll_rate: u64,
+    /// color names, `#rrggbb` or a 0-255 terminal palette index
+    pub header_color: String,
+    pub selection_color: String,
+    pub rm_color: String,
+}
+
+impl Default for TuiSettings {
+    fn default() -> Self {
+        Self {
+            poll_rate: 100,
+            header_color: "blue".into(),
+            selection_color: "cyan".into(),
+            rm_color: "red".into(),
+        }
+    }
+}
+
+impl TuiSettings {
+    fn style(color: &str) -> Style {
+        // colors are checked when the settings are loaded
+        Style::default().fg(color_from_arg(color).unwrap_or(Color::Reset))
+    }
+
+    pub fn header_style(&self) -> Style {
+        Self::style(&self.header_color)
+    }
+
+    pub fn selection_style(&self) -> Style {
+        Self::style(&self.selection_color)
+    }
+
+    pub fn rm_style(&self) -> Style {
+        Self::style(&self.rm_color)
+    }
+}
+
+impl Settings {
+    pub fn load(cfg: &ConfigDir) -> Result<Self> {
+        let path = ConfigPath::Settings.abs(cfg);
+        let raw = match read_to_string(&path) {
+            Ok(raw) => raw,
+            Err(e) if e.kind() == FileNotFound => return Ok(Self::default()),
+            Err(e) => return Err(e.into()),
+        };
+        toml::from_str(&raw)
+            .map_err(|e| e.to_string())
+            .and_then(|mut settings: Self| {
+                settings.check()?;
+                settings.profile = settings
+                    .profile
+                    .as_deref()
+                    .map(profile_from_arg)
+                    .transpose()?;
+                Ok(settings)
+            })
+            .map_err(|e| anyhow!("Invalid {}: {}", path.display(), e))
+    }
+
+    fn check(&self) -> Result<(), String> {
+        // checked like `--profile` is so it can't point outside of profiles/
+        if let Some(profile) = &self.profile {
+            profile_from_arg(profile).map_err(|e| format!("profile: {}", e))?;
+        }
+        if self.tui.poll_rate == 0 {
+            return Err("tui.poll_rate must be at least 1".into());
+        }
+        for (field, color) in [
+            ("header_color", &self.tui.header_color),
+            ("selection_color", &self.tui.selection_color),
+            ("rm_color", &self.tui.rm_color),
+        ]
+        .iter()
+        {
+            color_from_arg(color).map_err(|e| format!("tui.{}: {}", field, e))?;
+        }
+        Ok(())
+    }
+
+    pub fn save(&self, cfg: &ConfigDir) -> Result<()> {
+        let raw = toml::to_string(self)?;
+        let _lock = DbLock::acquire(cfg)?;
+        write_atomic(ConfigPath::Settings.abs(cfg), raw)
+    }
+}
+
+#[test]
+fn partial_settings_keep_defaults() {
+    let settings: Settings = toml::from_str("[defaults]\nscan_depth = 2\n").unwrap();
+    assert_eq!(settings.defaults.scan_depth, 2);
+    assert_eq!(settings.tui, TuiSettings::default());
+    assert!(settings.profile_fallback);
+
+    let bad: Settings = toml::from_str("[tui]\nrm_color = \"reddish\"\n").unwrap();
+    assert!(bad.check().is_err());
+    let multibyte: Settings = toml::from_str("[tui]\nrm_color = \"#aééb\"\n").unwrap();
+    assert!(multibyte.check().is_err());
+    let escaping: Settings = toml::from_str("profile = \"../../x\"\n").unwrap();
+    assert!(escaping.check().is_err());
+
+    let raw = toml::to_string(&Settings::default()).unwrap();
+    assert_eq!(
+        toml::from_str::<Settings>(&raw).unwrap(),
+        Settings::default()
+    );
+}
@@ -0,0 +1,427 @@
+//! sourceable scripts defining a shell function for every command key and alias
+
+use crate::{
+    prelude::*,
+    utils::{cmd::template::has_placeholders, os::home},
+};
+use std::{collections::BTreeMap, path::Path};
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Shell {
+    Bash,
+    Fish,
+    Zsh,
+}
+
+impl AsRef<str> for Shell {
+    fn as_ref(&self) -> &str {
+        match self {
+            Shell::Bash => "bash",
+            Shell::Fish => "fish",
+            Shell::Zsh => "zsh",
+        }
+    }
+}
+
+/// words that can't be redefined as functions without breaking the shell's grammar
+const RESERVED: &[&str] = &[
+    "!", "[[", "]]", "{", "}", "and", "begin", "case", "coproc", "do", "done", "elif", "else",
+    "end", "esac", "fi", "for", "function", "if", "in", "not", "or", "return", "select", "switch",
+    "then", "time", "until", "while",
+];
+
+fn is_function_name(key: &str) -> bool {
+    !key.is_empty()
+        && !key.starts_with('-')
+        && !RESERVED.contains(&key)
+        && key
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || "_-.+@%:".contains(c))
+}
+
+impl Shell {
+    /// ~/.config/fish/config.fish reads as fish and ~/.zshrc as zsh, anything else as bash
+    pub fn from_rc<P: AsRef<Path>>(path: P) -> Shell {
+        let path = path.as_ref().to_string_lossy();
+        if path.contains("fish") {
+            Shell::Fish
+        } else if path.contains("zsh") {
+            Shell::Zsh
+        } else {
+            Shell::Bash
+        }
+    }
+
+    pub fn quote(self, word: &str) -> String {
+        match self {
+            Shell::Bash | Shell::Zsh => format!("'{}'", word.replace('\'', r"'\''")),
+            Shell::Fish => format!("'{}'", word.replace('\\', r"\\").replace('\'', r"\'")),
+        }
+    }
+
+    fn function(self, name: &str, body: &str) -> String {
+        match self {
+            Shell::Bash | Shell::Zsh => format!("{}() {{ {} \"$@\"; }}\n", name, body),
+            Shell::Fish => format!("function {}; {} $argv; end\n", name, body),
+        }
+    }
+}
+
+/// commands flurry doesn't transform in any way run directly, everything else goes through
+/// `flurry go` so scans, filters, encoders, placeholders, permission checks, detaching, env and
+/// cwd still apply
+fn can_inline(cmd: &GeneratedCommand) -> bool {
+    cmd.scan_dir == ScanDirKind::None
+        && cmd.permissions == PermissionsKind::Any
+        && matches!(cmd.encoder, None | Some(EncoderKind::None))
+        && !cmd.query_which
+        && !cmd.detach
+        && cmd.env.is_none()
+        && cmd.cwd.is_none()
+        && !cmd
+            .dfl_args
+            .iter()
+            .flatten()
+            .any(|arg| has_placeholders(arg))
+}
+
+/// where the exported commands live, so `flurry go` finds them from any shell
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Source<'a> {
+    /// config root passed as `--db`, `None` for the default one
+    pub root: Option<&'a Path>,
+    pub profile: Option<&'a str>,
+}
+
+fn body(shell: Shell, key: &str, cmd: &GeneratedCommand, source: Source) -> String {
+    if can_inline(cmd) {
+        // `command` keeps a function named after its own bin from calling itself
+        let mut words = vec![shell.quote(&cmd.bin)];
+        words.extend(cmd.dfl_args.iter().flatten().map(|arg| shell.quote(arg)));
+        return format!("command {}", words.join(" "));
+    }
+    let mut words = vec!["flurry".to_owned()];
+    if let Some(root) = source.root {
+        words.push("--db".into());
+        words.push(shell.quote(&root.to_string_lossy()));
+    }
+    if let Some(profile) = source.profile {
+        words.push("--profile".into());
+        words.push(shell.quote(profile));
+    }
+    words.push("go".into());
+    words.push(shell.quote(key));
+    words.join(" ")
+}
+
+/// the script, along with keys that can't be used as function names
+pub fn script(shell: Shell, cmds: &GeneratedCommands, source: Source) -> (String, Vec<String>) {
+    let mut script = format!(
+        "# generated by `flurry export --shell {}`, source this file from your shell's startup file\n",
+        shell.as_ref()
+    );
+    let mut skipped = vec![];
+    let commands = match cmds.commands {
+        Some(ref commands) => commands,
+        None => return (script, skipped),
+    };
+
+    let mut names: Vec<(&String, &String)> = commands.keys().map(|key| (key, key)).collect();
+    names.sort();
+    let mut aliases: Vec<(&String, &String)> = cmds.aliases.iter().flatten().collect();
+    aliases.sort();
+    names.extend(aliases);
+
+    for (name, key) in names {
+        let cmd = match commands.get(key) {
+            Some(cmd) => cmd,
+            None => continue,
+        };
+        if !is_function_name(name) {
+            skipped.push(name.clone());
+            continue;
+        }
+        script.push_str(&shell.function(name, &body(shell, key, cmd, source)));
+    }
+    (script, skipped)
+}
+
+/// builtins change the shell they run in, which a generated command can't do
+const BUILTINS: &[&str] = &[
+    ".", "alias", "builtin", "cd", "eval", "export", "popd", "pushd", "set", "source", "unalias",
+    "unset",
+];
+
+/// splits `line` into words the way `shell` would, refusing anything that needs a shell to
+/// expand or run
+fn split_words(shell: Shell, line: &str) -> Result<Vec<String>, String> {
+    let mut words = vec![];
+    // `Some` once a word started, so '' still counts as one
+    let mut word: Option<String> = None;
+    let mut chars = line.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            c if c.is_whitespace() => words.extend(word.take()),
+            '#' if word.is_none() => break,
+            '\'' => {
+                let word = word.get_or_insert_with(String::new);
+                loop {
+                    match chars.next() {
+                        None => return Err("unterminated quote".into()),
+                        Some('\'') => break,
+                        Some('\\')
+                            if shell == Shell::Fish
+                                && matches!(chars.peek(), Some('\'' | '\\')) =>
+                        {
+                            word.extend(chars.next())
+                        }
+                        Some(c) => word.push(c),
+                    }
+                }
+            }
+            '"' => {
+                let word = word.get_or_insert_with(String::new);
+                loop {
+                    match chars.next() {
+                        None => return Err("unterminated quote".into()),
+                        Some('"') => break,
+                        Some('\\') if matches!(chars.peek(), Some('"' | '\\' | '$' | '`')) => {
+                            word.extend(chars.next())
+                        }
+                        Some(c @ '$') | Some(c @ '`') => {
+                            return Err(format!("uses `{}` expansion", c))
+                        }
+                        Some(c) => word.push(c),
+                    }
+                }
+            }
+            '\\' => match chars.next() {
+                Some(c) => word.get_or_insert_with(String::new).push(c),
+                None => return Err("continues on the next line".into()),
+            },
+            '$' | '`' => return Err(format!("uses `{}` expansion", c)),
+            ';' | '|' | '&' | '<' | '>' | '(' | ')' => {
+                return Err(format!("uses `{}`, which needs a shell", c))
+            }
+            '~' if word.is_none()
+                && !matches!(chars.peek(), Some(c) if *c != '/' && !c.is_whitespace()) =>
+            {
+                word = Some(home().to_string_lossy().into_owned())
+            }
+            c => word.get_or_insert_with(String::new).push(c),
+        }
+    }
+    words.extend(word);
+    Ok(words)
+}
+
+/// name and value of every definition on an `alias` line, bash and zsh take `name=value` pairs
+/// while fish also takes `alias name value...`
+fn alias_definitions(shell: Shell, words: &[String]) -> Result<Vec<(String, String)>, String> {
+    let mut words = words.iter().skip(1).peekable();
+    while let Some(option) = words.peek().filter(|word| word.starts_with('-')) {
+        match (shell, option.as_str()) {
+            (_, "--") => {
+                words.next();
+                break;
+            }
+            (Shell::Fish, "-s") | (Shell::Fish, "--save") => {}
+            (Shell::Zsh, "-g") | (Shell::Zsh, "-s") => {
+                return Err("global and suffix aliases aren't commands".into())
+            }
+            // listing options like -p or -L
+            _ => return Ok(vec![]),
+        }
+        words.next();
+    }
+
+    let words: Vec<&String> = words.collect();
+    let definitions = match words.first() {
+        None => vec![],
+        Some(first) if first.contains('=') => words
+            .iter()
+            .map(|word| match word.split_once('=') {
+                Some((name, value)) => Ok((name.to_owned(), value.to_owned())),
+                None => Err(format!("`{}` isn't a definition", word)),
+            })
+            .collect::<Result<_, _>>()?,
+        Some(_) if shell == Shell::Fish && words.len() > 1 => {
+            let value: Vec<&str> = words[1..].iter().map(|word| word.as_str()).collect();
+            vec![(words[0].clone(), value.join(" "))]
+        }
+        // prints the alias
+        Some(_) => vec![],
+    };
+    match definitions.iter().find(|(name, _)| name.is_empty()) {
+        Some(_) => Err("alias without a name".into()),
+        None => Ok(definitions),
+    }
+}
+
+/// the words `name` runs, expanding the aliases it starts with like the shell does
+fn expand_alias(
+    shell: Shell,
+    name: &str,
+    definitions: &BTreeMap<String, (usize, String)>,
+) -> Result<Vec<String>, String> {
+    let mut seen = vec![name];
+    let mut words = split_words(shell, &definitions[name].1)?;
+    while let Some((first, (_, value))) = words
+        .first()
+        .and_then(|first| definitions.get_key_value(first.as_str()))
+        .filter(|(first, _)| !seen.contains(&first.as_str()))
+    {
+        seen.push(first);
+        let rest = words.split_off(1);
+        words = split_words(shell, value)?;
+        words.extend(rest);
+    }
+    Ok(words)
+}
+
+/// commands for the aliases defined in an rc file, along with the line number and reason of
+/// every definition that couldn't be turned into one
+pub fn parse_aliases(
+    shell: Shell,
+    raw: &str,
+) -> (BTreeMap<String, GeneratedCommand>, Vec<(usize, String)>) {
+    let mut skipped = vec![];
+    // later definitions replace earlier ones, like they would in the shell
+    let mut definitions = BTreeMap::new();
+    for (n, line) in raw
+        .lines()
+        .enumerate()
+        .map(|(n, line)| (n + 1, line.trim()))
+    {
+        if line != "alias" && !line.starts_with("alias ") {
+            continue;
+        }
+        match split_words(shell, line).and_then(|words| alias_definitions(shell, &words)) {
+            Ok(found) => {
+                definitions.extend(found.into_iter().map(|(name, value)| (name, (n, value))))
+            }
+            Err(reason) => skipped.push((n, reason)),
+        }
+    }
+
+    let mut commands = BTreeMap::new();
+    for (name, (n, _)) in &definitions {
+        let words = match expand_alias(shell, name, &definitions) {
+            Ok(words) => words,
+            Err(reason) => {
+                skipped.push((*n, format!("`{}` {}", name, reason)));
+                continue;
+            }
+        };
+        let reason = match words.first() {
+            None => format!("`{}` is empty", name),
+            Some(bin) if BUILTINS.contains(&bin.as_str()) => {
+                format!("`{}` runs the shell builtin `{}`", name, bin)
+            }
+            Some(bin) if bin.contains('=') => {
+                format!("`{}` sets environment variables", name)
+            }
+            Some(_) => {
+                let mut words = words.into_iter();
+                commands.insert(
+                    name.clone(),
+                    GeneratedCommand {
+                        bin: words.next().unwrap_or_default(),
+                        dfl_args: Some(words.collect::<Vec<_>>()).filter(|args| !args.is_empty()),
+                        ..Default::default()
+                    },
+                );
+                continue;
+            }
+        };
+        skipped.push((*n, reason));
+    }
+    skipped.sort();
+    (commands, skipped)
+}
+
+#[test]
+fn quotes_and_routes_commands() {
+    let mut commands = HashMap::new();
+    commands.insert(
+        "greet".to_owned(),
+        GeneratedCommand::running("echo", &["it's"]),
+    );
+    commands.insert(
+        "music".to_owned(),
+        GeneratedCommand {
+            bin: "mpv".into(),
+            scan_dir: ScanDirKind::Depth(2),
+            ..Default::default()
+        },
+    );
+    commands.insert("if".to_owned(), GeneratedCommand::default());
+    let mut aliases = HashMap::new();
+    aliases.insert("m".to_owned(), "music".to_owned());
+    let cmds = GeneratedCommands {
+        commands: Some(commands),
+        aliases: Some(aliases),
+    };
+
+    let (bash, skipped) = script(Shell::Bash, &cmds, Source::default());
+    assert!(bash.contains("greet() { command 'echo' 'it'\\''s' \"$@\"; }\n"));
+    assert!(bash.contains("music() { flurry go 'music' \"$@\"; }\n"));
+    assert!(bash.contains("m() { flurry go 'music' \"$@\"; }\n"));
+    assert_eq!(skipped, vec!["if".to_owned()]);
+
+    let work = Source {
+        root: None,
+        profile: Some("work"),
+    };
+    let (fish, _) = script(Shell::Fish, &cmds, work);
+    assert!(fish.contains("function greet; command 'echo' 'it\\'s' $argv; end\n"));
+    assert!(fish.contains("function m; flurry --profile 'work' go 'music' $argv; end\n"));
+
+    let elsewhere = Source {
+        root: Some(Path::new("/srv/my flurry")),
+        profile: Some("work"),
+    };
+    let (zsh, _) = script(Shell::Zsh, &cmds, elsewhere);
+    assert!(
+        zsh.contains("m() { flurry --db '/srv/my flurry' --profile 'work' go 'music' \"$@\"; }\n")
+    );
+}
+
+#[test]
+fn parses_rc_aliases() {
+    let rc = r#"
+export PATH="$HOME/bin:$PATH"
+alias ll='ls -la' la="ls -A"  # listings
+alias lla='ll --color'
+alias gs="git status $1"
+alias up='cd ..'
+alias g=git
+alias -- tree="tree -C"
+alias -p
+alias oops='unterminated
+alias x='echo a | wc'
+"#;
+    let (commands, skipped) = parse_aliases(Shell::Bash, rc);
+    let words = |key: &str| {
+        let cmd = &commands[key];
+        let mut words = vec![cmd.bin.clone()];
+        words.extend(cmd.dfl_args.iter().flatten().cloned());
+        words
+    };
+    assert_eq!(words("ll"), vec!["ls", "-la"]);
+    assert_eq!(words("la"), vec!["ls", "-A"]);
+    assert_eq!(words("lla"), vec!["ls", "-la", "--color"]);
+    assert_eq!(words("g"), vec!["git"]);
+    assert_eq!(words("tree"), vec!["tree", "-C"]);
+    assert_eq!(commands.len(), 5);
+    assert_eq!(
+        skipped.iter().map(|(n, _)| *n).collect::<Vec<_>>(),
+        vec![5, 6, 10, 11]
+    );
+
+    let fish = "alias gl 'git log --oneline'\nalias --save e 'echo \\'hi there\\''\n";
+    let (commands, skipped) = parse_aliases(Shell::Fish, fish);
+    assert_eq!(commands["gl"].bin, "git");
+    assert_eq!(commands["e"].dfl_args, Some(vec!["hi there".to_owned()]));
+    assert!(skipped.is_empty());
+}
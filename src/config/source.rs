@@ -0,0 +1,827 @@
+//! commands.toml, the hand editable source the rkyv cache is compiled from
+//!
+//! ```toml
+//! [commands.music]
+//! bin = "mpv"
+//! args = ["--shuffle"]
+//! aliases = ["m"]
+//! tags = ["media"]
+//! encoder = "url"
+//! permissions = "user"
+//! query_which = true
+//! detach = true
+//! scan_depth = 3
+//! cwd = "~/music"
+//! unset_env = ["DISPLAY"]
+//!
+//! [commands.music.env]
+//! PULSE_SINK = "headphones"
+//!
+//! [commands.music.filters]
+//! exts = ["mp3", "flac"]
+//! file_type = "files"
+//! regex = ["^live"]
+//! ```
+//!
+//! Scans apply filters in order, so filters the table above can't reproduce, like two `exts`
+//! lists or a regex ahead of the file type, are written as an array of tables instead:
+//!
+//! ```toml
+//! [[commands.music.filters]]
+//! regex = ["^live"]
+//!
+//! [[commands.music.filters]]
+//! file_type = "files"
+//! ```
+//!
+//! Changes made through flurry are written into the file as it is, so comments, ordering and
+//! layout survive everything but the commands or fields they belong to being removed.
+
+use super::{error::DbError, header::DbHeader};
+use crate::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fs::{metadata, read, OpenOptions},
+    path::Path,
+};
+use toml_edit::{DocumentMut, Item, Table};
+
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct SourceCommand {
+    bin: String,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    args: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    aliases: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    tags: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    encoder: Option<EncoderKind>,
+    #[serde(default, skip_serializing_if = "is_any")]
+    permissions: PermissionsKind,
+    #[serde(default, skip_serializing_if = "is_false")]
+    query_which: bool,
+    #[serde(default, skip_serializing_if = "is_false")]
+    detach: bool,
+    /// 0 scans nothing, so it's read as scanning turned off the same way the cli and settings
+    /// treat it, and a `ScanDirKind::Depth(0)` comes back as `ScanDirKind::None`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    scan_depth: Option<u8>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    cwd: Option<String>,
+    /// toml has no null, so unset variables get a list of their own
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    unset_env: Vec<String>,
+    /// passed on as they are, unlike `cwd` a leading `~` isn't expanded
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    env: BTreeMap<String, String>,
+    #[serde(default, skip_serializing_if = "SourceFilters::is_empty")]
+    filters: SourceFilters,
+}
+
+/// toml can't hold enums with data, so filters are grouped by kind when that keeps their
+/// meaning and listed in order otherwise
+#[derive(Clone, Debug, PartialEq, Serialize)]
+#[serde(untagged)]
+enum SourceFilters {
+    Grouped(GroupedFilters),
+    Ordered(Vec<GroupedFilters>),
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+struct GroupedFilters {
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    exts: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    file_type: Option<FileTypeFilter>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    regex: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    raw: Vec<String>,
+}
+
+impl Default for SourceFilters {
+    fn default() -> Self {
+        SourceFilters::Grouped(GroupedFilters::default())
+    }
+}
+
+/// a table is grouped and an array ordered, dispatched by hand so errors from either keep
+/// their message instead of untagged's "did not match any variant"
+impl<'de> Deserialize<'de> for SourceFilters {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        use serde::de::{
+            value::{MapAccessDeserializer, SeqAccessDeserializer},
+            MapAccess, SeqAccess, Visitor,
+        };
+
+        struct FiltersVisitor;
+
+        impl<'de> Visitor<'de> for FiltersVisitor {
+            type Value = SourceFilters;
+
+            fn expecting(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+                f.write_str("a table of filters or an array of them")
+            }
+
+            fn visit_map<A: MapAccess<'de>>(self, map: A) -> Result<Self::Value, A::Error> {
+                GroupedFilters::deserialize(MapAccessDeserializer::new(map))
+                    .map(SourceFilters::Grouped)
+            }
+
+            fn visit_seq<A: SeqAccess<'de>>(self, seq: A) -> Result<Self::Value, A::Error> {
+                Vec::deserialize(SeqAccessDeserializer::new(seq)).map(SourceFilters::Ordered)
+            }
+        }
+
+        deserializer.deserialize_any(FiltersVisitor)
+    }
+}
+
+impl GroupedFilters {
+    fn new(filters: &[FilterKind]) -> Self {
+        let mut grouped = Self::default();
+        for filter in filters {
+            match filter {
+                FilterKind::Exts(exts) => grouped.exts.extend(exts.iter().cloned()),
+                FilterKind::FileType(file_type) => grouped.file_type = Some(file_type.clone()),
+                FilterKind::RegEx(regex) => grouped.regex.push(regex.clone()),
+                FilterKind::Raw(raw) => grouped.raw.push(raw.clone()),
+                FilterKind::None => {}
+            }
+        }
+        grouped
+    }
+
+    fn into_vec(self) -> Vec<FilterKind> {
+        let mut filters = vec![];
+        if !self.exts.is_empty() {
+            filters.push(FilterKind::Exts(self.exts));
+        }
+        filters.extend(self.file_type.map(FilterKind::FileType));
+        filters.extend(self.regex.into_iter().map(FilterKind::RegEx));
+        filters.extend(self.raw.into_iter().map(FilterKind::Raw));
+        filters
+    }
+}
+
+impl SourceFilters {
+    fn new(filters: &FiltersKind) -> Self {
+        let list = match filters {
+            FiltersKind::One(filter) => std::slice::from_ref(filter),
+            FiltersKind::Many(filters) => filters.as_slice(),
+            FiltersKind::None => &[],
+        };
+
+        let grouped = SourceFilters::Grouped(GroupedFilters::new(list));
+        if grouped.clone().into_filters() == *filters {
+            return grouped;
+        }
+        // a table per filter, `FilterKind::None` only stands in for a missing filter and has
+        // no source form
+        SourceFilters::Ordered(
+            list.iter()
+                .filter(|filter| **filter != FilterKind::None)
+                .map(|filter| GroupedFilters::new(std::slice::from_ref(filter)))
+                .collect(),
+        )
+    }
+
+    fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+
+    fn into_filters(self) -> FiltersKind {
+        match self {
+            SourceFilters::Grouped(grouped) => {
+                let mut filters = grouped.into_vec();
+                match filters.len() {
+                    0 => FiltersKind::None,
+                    1 => FiltersKind::One(filters.remove(0)),
+                    _ => FiltersKind::Many(filters),
+                }
+            }
+            SourceFilters::Ordered(tables) => FiltersKind::Many(
+                tables
+                    .into_iter()
+                    .flat_map(GroupedFilters::into_vec)
+                    .collect(),
+            ),
+        }
+    }
+}
+
+fn is_any(permissions: &PermissionsKind) -> bool {
+    *permissions == PermissionsKind::Any
+}
+
+fn is_false(switch: &bool) -> bool {
+    !switch
+}
+
+#[derive(Default, Serialize)]
+struct SourceFile {
+    commands: BTreeMap<String, SourceCommand>,
+}
+
+impl SourceCommand {
+    fn new(cmd: &GeneratedCommand, aliases: Vec<String>) -> Self {
+        let vars = cmd.env.clone().unwrap_or_default();
+        Self {
+            bin: cmd.bin.clone(),
+            args: cmd.dfl_args.clone().unwrap_or_default(),
+            aliases,
+            tags: cmd.tags.clone().unwrap_or_default(),
+            encoder: cmd.encoder.clone(),
+            permissions: cmd.permissions.clone(),
+            query_which: cmd.query_which,
+            detach: cmd.detach,
+            scan_depth: match cmd.scan_dir {
+                ScanDirKind::Depth(depth) => Some(depth),
+                ScanDirKind::None => None,
+            },
+            cwd: cmd.cwd.clone(),
+            unset_env: {
+                let mut unset: Vec<String> = vars
+                    .iter()
+                    .filter(|(_, value)| value.is_none())
+                    .map(|(key, _)| key.clone())
+                    .collect();
+                unset.sort();
+                unset
+            },
+            env: vars
+                .iter()
+                .filter_map(|(key, value)| Some((key.clone(), value.clone()?)))
+                .collect(),
+            filters: SourceFilters::new(&cmd.filter),
+        }
+    }
+
+    pub fn bin(&self) -> &str {
+        &self.bin
+    }
+
+    pub fn aliases(&self) -> &[String] {
+        &self.aliases
+    }
+
+    pub fn into_cmd(self) -> GeneratedCommand {
+        GeneratedCommand {
+            bin: self.bin,
+            dfl_args: Some(self.args).filter(|args| !args.is_empty()),
+            encoder: self.encoder,
+            aliases: Some(self.aliases).filter(|aliases| !aliases.is_empty()),
+            tags: Some(self.tags).filter(|tags| !tags.is_empty()),
+            filter: self.filters.into_filters(),
+            permissions: self.permissions,
+            query_which: self.query_which,
+            detach: self.detach,
+            env: {
+                let mut vars: HashMap<String, Option<String>> = self
+                    .env
+                    .into_iter()
+                    .map(|(key, value)| (key, Some(value)))
+                    .collect();
+                vars.extend(self.unset_env.into_iter().map(|key| (key, None)));
+                Some(vars).filter(|vars| !vars.is_empty())
+            },
+            cwd: self.cwd,
+            scan_dir: match self.scan_depth {
+                None | Some(0) => ScanDirKind::None,
+                Some(depth) => ScanDirKind::Depth(depth),
+            },
+        }
+    }
+}
+
+/// `key` as it would appear in commands.toml, along with the aliases pointing at it
+pub fn snapshot(cmds: &GeneratedCommands, key: &str) -> Option<SourceCommand> {
+    let cmd = cmds.commands.as_ref()?.get(key)?;
+    let mut aliases: Vec<String> = cmds
+        .aliases
+        .iter()
+        .flatten()
+        .filter(|(_, target)| *target == key)
+        .map(|(alias, _)| alias.clone())
+        .collect();
+    aliases.sort();
+    Some(SourceCommand::new(cmd, aliases))
+}
+
+/// a top level field of commands.toml, or one of its filters, that differs between two
+/// versions of a command. Fields left at their default are `None`.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct FieldChange {
+    pub field: String,
+    pub old: Option<serde_json::Value>,
+    pub new: Option<serde_json::Value>,
+}
+
+/// a field's value as diff and sync print it
+pub fn show_value(value: &Option<serde_json::Value>) -> String {
+    match value {
+        Some(value) => value.to_string(),
+        None => "(none)".into(),
+    }
+}
+
+/// the fields of a command as json, filters are a table of their own so each one is a field
+/// named `filters.<kind>`, ordered filters are a single `filters` field
+pub fn fields(cmd: &SourceCommand) -> BTreeMap<String, serde_json::Value> {
+    let mut fields = BTreeMap::new();
+    if let Ok(serde_json::Value::Object(table)) = serde_json::to_value(cmd) {
+        for (field, value) in table {
+            match value {
+                serde_json::Value::Object(nested) => {
+                    fields.extend(
+                        nested
+                            .into_iter()
+                            .map(|(name, value)| (format!("{}.{}", field, name), value)),
+                    );
+                }
+                value => {
+                    fields.insert(field, value);
+                }
+            }
+        }
+    }
+    fields
+}
+
+/// the inverse of `fields`
+pub fn from_fields(fields: BTreeMap<String, serde_json::Value>) -> Result<SourceCommand> {
+    let mut table = serde_json::Map::new();
+    for (field, value) in fields {
+        match field.split_once('.') {
+            Some((field, name)) => {
+                if let serde_json::Value::Object(nested) = table
+                    .entry(field)
+                    .or_insert_with(|| serde_json::Value::Object(Default::default()))
+                {
+                    nested.insert(name.to_owned(), value);
+                }
+            }
+            None => {
+                table.insert(field, value);
+            }
+        }
+    }
+    Ok(serde_json::from_value(serde_json::Value::Object(table))?)
+}
+
+pub fn changed_fields(before: &SourceCommand, after: &SourceCommand) -> Vec<FieldChange> {
+    let (mut before, mut after) = (fields(before), fields(after));
+    let names: BTreeSet<String> = before.keys().chain(after.keys()).cloned().collect();
+    names
+        .into_iter()
+        .filter_map(|field| {
+            let (old, new) = (before.remove(&field), after.remove(&field));
+            if old == new {
+                return None;
+            }
+            Some(FieldChange { field, old, new })
+        })
+        .collect()
+}
+
+/// aliases come from the alias map since that's what lookups use
+fn source_file(cmds: &GeneratedCommands) -> SourceFile {
+    let mut aliases: HashMap<&str, Vec<String>> = HashMap::new();
+    if let Some(ref map) = cmds.aliases {
+        for (alias, key) in map {
+            aliases.entry(key).or_default().push(alias.clone());
+        }
+    }
+
+    let mut file = SourceFile::default();
+    if let Some(ref commands) = cmds.commands {
+        for (key, cmd) in commands {
+            let mut cmd_aliases = aliases.remove(key.as_str()).unwrap_or_default();
+            cmd_aliases.sort();
+            file.commands
+                .insert(key.clone(), SourceCommand::new(cmd, cmd_aliases));
+        }
+    }
+    file
+}
+
+/// renders commands as toml
+pub fn render(cmds: &GeneratedCommands) -> Result<String> {
+    Ok(toml::to_string(&source_file(cmds))?)
+}
+
+/// renders commands over an existing commands.toml. Commands and fields that didn't change keep
+/// their text, changed values keep the comments around them, and new ones are appended after
+/// the rest. Sources that don't parse are rendered from scratch.
+pub fn render_over(existing: &str, cmds: &GeneratedCommands) -> Result<String> {
+    let fresh: DocumentMut = render(cmds)?.parse()?;
+    let mut doc: DocumentMut = match existing.parse() {
+        Ok(doc) => doc,
+        Err(_) => return Ok(fresh.to_string()),
+    };
+    // the `[commands]` header sources start out with isn't kept once there are commands
+    if doc
+        .get("commands")
+        .and_then(Item::as_table)
+        .is_some_and(Table::is_empty)
+    {
+        doc.remove("commands");
+    }
+    merge_table(doc.as_table_mut(), fresh.as_table());
+    Ok(doc.to_string())
+}
+
+fn merge_table(old: &mut Table, new: &Table) {
+    let last = last_position(old);
+    old.retain(|key, _| new.contains_key(key));
+    for (key, item) in new.iter() {
+        match old.get_mut(key) {
+            Some(Item::Table(old_table)) if item.is_table() => {
+                merge_table(old_table, item.as_table().unwrap())
+            }
+            Some(old_item) if plain(old_item).is_some() && plain(old_item) == plain(item) => {}
+            Some(old_item) => {
+                let decor = old_item.as_value().map(|value| value.decor().clone());
+                *old_item = item.clone();
+                match (old_item.as_value_mut(), decor) {
+                    (Some(value), Some(decor)) => *value.decor_mut() = decor,
+                    _ => place(old_item, last),
+                }
+            }
+            None => {
+                let mut item = item.clone();
+                place(&mut item, last);
+                old.insert(key, item);
+            }
+        }
+    }
+}
+
+/// the item as toml sees it, without comments or layout
+fn plain(item: &Item) -> Option<toml::Value> {
+    let mut doc = DocumentMut::new();
+    doc.insert("v", item.clone());
+    toml::from_str::<toml::Value>(&doc.to_string())
+        .ok()?
+        .as_table_mut()?
+        .remove("v")
+}
+
+/// where the last table inside `table` sits in the document
+fn last_position(table: &Table) -> usize {
+    table
+        .iter()
+        .filter_map(|(_, item)| item.as_table())
+        .map(last_position)
+        .chain(table.position())
+        .max()
+        .unwrap_or(0)
+}
+
+/// tables come from the fresh render and carry its positions, these put them after `last`,
+/// a blank line apart from the tables already there
+fn place(item: &mut Item, last: usize) {
+    let place_table = |table: &mut Table| {
+        table.set_position(last);
+        if last > 0 {
+            table.decor_mut().set_prefix("\n");
+        }
+    };
+    match item {
+        Item::Table(table) => {
+            place_table(table);
+            for (_, item) in table.iter_mut() {
+                place(item, last);
+            }
+        }
+        Item::ArrayOfTables(tables) => tables.iter_mut().for_each(place_table),
+        _ => {}
+    }
+}
+
+/// renders commands as json, laid out the same way as commands.toml
+pub fn render_json(cmds: &GeneratedCommands) -> Result<String> {
+    Ok(serde_json::to_string_pretty(&source_file(cmds))? + "\n")
+}
+
+fn invalid((line, col): (usize, usize), path: &Path, reason: String) -> DbError {
+    DbError::InvalidSource {
+        path: path.to_owned(),
+        line,
+        col,
+        reason,
+    }
+}
+
+/// both parsers append the position to their messages, it's reported separately
+fn strip_position(reason: String) -> String {
+    match reason.rfind(" at line ") {
+        Some(end) => reason[..end].to_owned(),
+        None => reason,
+    }
+}
+
+/// parses commands.toml, errors point at the offending line and column of `path`
+pub fn compile(raw: &str, path: &Path) -> Result<GeneratedCommands> {
+    // parsing per command keeps errors pointing at the command, toml only tracks
+    // positions for syntax errors
+    #[derive(Deserialize)]
+    #[serde(deny_unknown_fields)]
+    struct RawFile {
+        #[serde(default)]
+        commands: BTreeMap<String, toml::Value>,
+    }
+    let file: RawFile = toml::from_str(raw).map_err(|e| {
+        let position = e
+            .line_col()
+            .map_or((1, 1), |(line, col)| (line + 1, col + 1));
+        invalid(position, path, strip_position(e.to_string()))
+    })?;
+
+    let mut parsed = vec![];
+    for (key, value) in file.commands {
+        let cmd: SourceCommand = value.try_into().map_err(|e| {
+            let reason = format!("command `{}`: {}", key, e);
+            invalid(locate(raw, &key, None), path, reason)
+        })?;
+        parsed.push((key, cmd));
+    }
+    assemble(parsed, path, |key, alias| locate(raw, key, alias))
+}
+
+/// parses a json export, errors point at the offending line and column of `path`
+pub fn compile_json(raw: &str, path: &Path) -> Result<GeneratedCommands> {
+    #[derive(Deserialize)]
+    #[serde(deny_unknown_fields)]
+    struct RawFile {
+        #[serde(default)]
+        commands: BTreeMap<String, serde_json::Value>,
+    }
+    let file: RawFile = serde_json::from_str(raw).map_err(|e| {
+        let position = (e.line().max(1), e.column().max(1));
+        invalid(position, path, strip_position(e.to_string()))
+    })?;
+
+    let mut parsed = vec![];
+    for (key, value) in file.commands {
+        let cmd: SourceCommand = serde_json::from_value(value).map_err(|e| {
+            let reason = format!("command `{}`: {}", key, e);
+            invalid(locate_json(raw, &key, None), path, reason)
+        })?;
+        parsed.push((key, cmd));
+    }
+    assemble(parsed, path, |key, alias| locate_json(raw, key, alias))
+}
+
+/// builds the database out of parsed commands, rejecting aliases claimed twice or shadowing
+/// a command. `locate` finds a command, or one of its aliases, in the source.
+fn assemble(
+    parsed: Vec<(String, SourceCommand)>,
+    path: &Path,
+    locate: impl Fn(&str, Option<&str>) -> (usize, usize),
+) -> Result<GeneratedCommands> {
+    let mut commands = HashMap::new();
+    let mut aliases = HashMap::new();
+    for (key, cmd) in parsed {
+        for alias in &cmd.aliases {
+            if let Some(owner) = aliases.insert(alias.clone(), key.clone()) {
+                let reason = format!("alias `{}` is already used by `{}`", alias, owner);
+                return Err(invalid(locate(&key, Some(alias)), path, reason).into());
+            }
+        }
+        commands.insert(key, cmd.into_cmd());
+    }
+    for (alias, owner) in &aliases {
+        if commands.contains_key(alias) {
+            let reason = format!("alias `{}` shadows the command of the same name", alias);
+            return Err(invalid(locate(owner, Some(alias)), path, reason).into());
+        }
+    }
+
+    // lookups on empty archived maps divide by zero in rkyv 0.5
+    Ok(GeneratedCommands {
+        commands: Some(commands).filter(|commands| !commands.is_empty()),
+        aliases: Some(aliases).filter(|aliases| !aliases.is_empty()),
+    })
+}
+
+/// before the toml source existed commands.toml held the rkyv archive itself,
+/// toml can't contain NUL so any archive is told apart by its header or padding
+pub fn is_archive(cfg: &ConfigDir) -> Result<bool> {
+    let bytes = match read(ConfigPath::Commands.abs(cfg)) {
+        Ok(bytes) => bytes,
+        Err(e) if e.kind() == FileNotFound => return Ok(false),
+        Err(e) => return Err(e.into()),
+    };
+    Ok(looks_like_archive(&bytes))
+}
+
+pub fn looks_like_archive(bytes: &[u8]) -> bool {
+    DbHeader::has_magic(bytes) || bytes.contains(&0)
+}
+
+/// the cache carries the mtime of the source it was compiled from
+pub fn cache_is_stale(cfg: &ConfigDir) -> Result<bool> {
+    let source = metadata(ConfigPath::Commands.abs(cfg))?.modified()?;
+    match metadata(ConfigPath::Cache.abs(cfg)) {
+        Ok(cache) => Ok(cache.modified()? != source),
+        Err(e) if e.kind() == FileNotFound => Ok(true),
+        Err(e) => Err(e.into()),
+    }
+}
+
+pub fn mark_cache_fresh(cfg: &ConfigDir) -> Result<()> {
+    let source = metadata(ConfigPath::Commands.abs(cfg))?.modified()?;
+    OpenOptions::new()
+        .write(true)
+        .open(ConfigPath::Cache.abs(cfg))?
+        .set_modified(source)?;
+    Ok(())
+}
+
+/// best effort position of a command's table header, or of `needle` inside that table
+fn locate(raw: &str, key: &str, needle: Option<&str>) -> (usize, usize) {
+    let headers = [
+        format!("[commands.{}]", key),
+        format!("[commands.\"{}\"]", key),
+    ];
+    let mut lines = raw.lines().enumerate();
+    let header = lines
+        .by_ref()
+        .find(|(_, line)| headers.iter().any(|header| line.trim() == header));
+    let (header_line, _) = match header {
+        Some(found) => found,
+        None => return (1, 1),
+    };
+
+    if let Some(needle) = needle {
+        let quoted = format!("\"{}\"", needle);
+        for (n, line) in lines.take_while(|(_, line)| !line.trim_start().starts_with('[')) {
+            if let Some(col) = line.find(&quoted) {
+                return (n + 1, col + 1);
+            }
+        }
+    }
+    (header_line + 1, 1)
+}
+
+/// like `locate`, for the member holding a command in a pretty printed json export
+fn locate_json(raw: &str, key: &str, needle: Option<&str>) -> (usize, usize) {
+    let member = format!("\"{}\":", key);
+    let indent = |line: &str| line.len() - line.trim_start().len();
+    let mut lines = raw.lines().enumerate();
+    let (member_line, member_indent) = match lines
+        .by_ref()
+        .find(|(_, line)| line.trim_start().starts_with(&member))
+    {
+        Some((n, line)) => (n, indent(line)),
+        None => return (1, 1),
+    };
+
+    if let Some(needle) = needle {
+        let quoted = format!("\"{}\"", needle);
+        for (n, line) in lines.take_while(|(_, line)| indent(line) > member_indent) {
+            if let Some(col) = line.find(&quoted) {
+                return (n + 1, col + 1);
+            }
+        }
+    }
+    (member_line + 1, member_indent + 1)
+}
+
+#[test]
+fn round_trips_and_points_at_errors() {
+    let mut commands = HashMap::new();
+    commands.insert(
+        "music".to_owned(),
+        GeneratedCommand {
+            bin: "mpv".into(),
+            dfl_args: Some(vec!["--shuffle".into()]),
+            encoder: Some(EncoderKind::Url),
+            aliases: Some(vec!["m".into()]),
+            filter: FiltersKind::Many(vec![
+                FilterKind::Exts(vec!["mp3".into()]),
+                FilterKind::FileType(FileTypeFilter::Files),
+            ]),
+            permissions: PermissionsKind::User,
+            query_which: true,
+            scan_dir: ScanDirKind::Depth(3),
+            tags: Some(vec!["media".into()]),
+            detach: true,
+            env: Some(
+                vec![
+                    ("PULSE_SINK".to_owned(), Some("headphones".to_owned())),
+                    ("DISPLAY".to_owned(), None),
+                ]
+                .into_iter()
+                .collect(),
+            ),
+            cwd: Some("~/music".into()),
+        },
+    );
+    // filters the grouped table would reorder or merge keep their order
+    commands.insert(
+        "live".to_owned(),
+        GeneratedCommand {
+            bin: "mpv".into(),
+            filter: FiltersKind::Many(vec![
+                FilterKind::RegEx("^live".into()),
+                FilterKind::FileType(FileTypeFilter::Files),
+                FilterKind::Exts(vec!["mp3".into()]),
+                FilterKind::Exts(vec!["flac".into()]),
+            ]),
+            scan_dir: ScanDirKind::Depth(1),
+            ..Default::default()
+        },
+    );
+    commands.insert(
+        "one".to_owned(),
+        GeneratedCommand {
+            bin: "mpv".into(),
+            filter: FiltersKind::Many(vec![FilterKind::Exts(vec!["mp3".into()])]),
+            ..Default::default()
+        },
+    );
+    let mut aliases = HashMap::new();
+    aliases.insert("m".to_owned(), "music".to_owned());
+    let mut cmds = GeneratedCommands {
+        commands: Some(commands),
+        aliases: Some(aliases),
+    };
+
+    let path = Path::new("commands.toml");
+    let raw = render(&cmds).unwrap();
+    assert!(raw.contains("[[commands.live.filters]]\nregex = [\"^live\"]"));
+    assert_eq!(compile(&raw, path).unwrap(), cmds);
+    let raw = render_json(&cmds).unwrap();
+    assert_eq!(compile_json(&raw, path).unwrap(), cmds);
+
+    // depth 0 scans nothing and is read back as scanning turned off
+    let commands = cmds.commands.as_mut().unwrap();
+    commands.get_mut("live").unwrap().scan_dir = ScanDirKind::Depth(0);
+    let compiled = compile(&render(&cmds).unwrap(), path).unwrap();
+    assert_eq!(compiled.get("live").unwrap().scan_dir, ScanDirKind::None);
+
+    let bad_encoder =
+        "[commands.ls]\nbin = \"ls\"\n\n[commands.web]\nbin = \"firefox\"\nencoder = \"base64\"\n";
+    match compile(bad_encoder, path)
+        .unwrap_err()
+        .downcast::<DbError>()
+    {
+        Ok(DbError::InvalidSource { line, col, .. }) => assert_eq!((line, col), (4, 1)),
+        other => panic!("unexpected {:?}", other),
+    }
+
+    let taken_alias = "[commands.a]\nbin = \"a\"\naliases = [\"x\"]\n\n[commands.b]\nbin = \"b\"\naliases = [\"y\", \"x\"]\n";
+    match compile(taken_alias, path)
+        .unwrap_err()
+        .downcast::<DbError>()
+    {
+        Ok(DbError::InvalidSource { line, col, .. }) => assert_eq!((line, col), (7, 17)),
+        other => panic!("unexpected {:?}", other),
+    }
+
+    let taken_json = "{\n  \"commands\": {\n    \"a\": {\n      \"bin\": \"a\",\n      \"aliases\": [\"x\"]\n    },\n    \"b\": {\n      \"bin\": \"b\",\n      \"aliases\": [\"x\"]\n    }\n  }\n}\n";
+    match compile_json(taken_json, path)
+        .unwrap_err()
+        .downcast::<DbError>()
+    {
+        Ok(DbError::InvalidSource { line, col, .. }) => assert_eq!((line, col), (9, 19)),
+        other => panic!("unexpected {:?}", other),
+    }
+
+    match compile("[commands.ls\nbin = 1", path)
+        .unwrap_err()
+        .downcast::<DbError>()
+    {
+        Ok(DbError::InvalidSource { line, .. }) => assert_eq!(line, 1),
+        other => panic!("unexpected {:?}", other),
+    }
+}
+
+#[test]
+fn edits_keep_comments_and_layout() {
+    let existing = "# hand kept\n\n[commands.zed]\nbin = \"zed\"   # the fast one\nargs = [\n    \"--new\",  # new window\n]\n\n[commands.music]\nbin = \"mpv\" # player\n\n# going away\n[commands.gone]\nbin = \"true\"\n";
+    let path = Path::new("commands.toml");
+    let mut cmds = compile(existing, path).unwrap();
+    let commands = cmds.commands.as_mut().unwrap();
+    commands.remove("gone");
+    commands.get_mut("music").unwrap().bin = "vlc".into();
+    commands.insert("new".to_owned(), GeneratedCommand::running("echo", &[]));
+
+    let edited = render_over(existing, &cmds).unwrap();
+    assert_eq!(
+        edited,
+        "# hand kept\n\n[commands.zed]\nbin = \"zed\"   # the fast one\nargs = [\n    \"--new\",  # new window\n]\n\n[commands.music]\nbin = \"vlc\" # player\n\n[commands.new]\nbin = \"echo\"\n"
+    );
+    assert_eq!(compile(&edited, path).unwrap(), cmds);
+    // sources that don't parse are replaced
+    assert_eq!(
+        render_over("[commands", &cmds).unwrap(),
+        render(&cmds).unwrap()
+    );
+}
@@ -1,8 +1,10 @@
 use crate::utils::traits::Valid;
+use bytecheck::CheckBytes;
 use rkyv::{core_impl::ArchivedOption, Archive, Deserialize, Serialize};
 use std::collections::HashMap;
 
 #[derive(Debug, Default, PartialEq, Archive, Deserialize, Serialize)]
+#[archive(derive(CheckBytes))]
 pub struct GeneratedCommands {
     pub commands: Option<HashMap<String, GeneratedCommand>>,
     pub aliases: Option<HashMap<String, String>>,
@@ -41,21 +43,27 @@ impl ArchivedGeneratedCommands {
     pub fn contains_key<S: AsRef<str>>(&self, key: S) -> bool {
         let key = key.as_ref();
         if let ArchivedOption::Some(commands) = &self.commands {
-            if commands.contains_key(key) {
+            if !commands.is_empty() && commands.contains_key(key) {
                 return true;
             }
         }
         if let ArchivedOption::Some(aliases) = &self.aliases {
-            return aliases.contains_key(key);
+            return !aliases.is_empty() && aliases.contains_key(key);
         }
         false
     }
 
     pub fn get<S: AsRef<str>>(&self, key: S) -> Option<&ArchivedGeneratedCommand> {
         if let ArchivedOption::Some(commands) = &self.commands {
+            if commands.is_empty() {
+                return None;
+            }
             let key = key.as_ref();
             return commands.get(key).or_else(|| {
                 if let ArchivedOption::Some(aliases) = &self.aliases {
+                    if aliases.is_empty() {
+                        return None;
+                    }
                     aliases.get(key).and_then(|key| commands.get(key))
                 } else {
                     None
@@ -65,12 +73,20 @@ impl ArchivedGeneratedCommands {
         None
     }
 
+    /// looks `key` up in each set in turn, e.g. a profile followed by the default set
+    pub fn get_chained<'a, S: AsRef<str>>(
+        chain: &[&'a Self],
+        key: S,
+    ) -> Option<&'a ArchivedGeneratedCommand> {
+        chain.iter().find_map(|cmds| cmds.get(key.as_ref()))
+    }
+
     pub fn is_alias<S: AsRef<str>>(&self, key: S) -> bool {
         if !self.contains_key(&key) {
             return false;
         }
         if let ArchivedOption::Some(aliases) = &self.aliases {
-            return aliases.contains_key(key.as_ref());
+            return !aliases.is_empty() && aliases.contains_key(key.as_ref());
         }
 
         false
@@ -78,6 +94,9 @@ impl ArchivedGeneratedCommands {
 }
 
 #[derive(Clone, Debug, PartialEq, Archive, Deserialize, Serialize)]
+#[archive(derive(CheckBytes))]
+#[derive(serde::Deserialize, serde::Serialize)]
+#[serde(rename_all = "lowercase")]
 pub enum PermissionsKind {
     Any,
     Group,
@@ -117,6 +136,9 @@ impl Default for PermissionsKind {
 }
 
 #[derive(Clone, Debug, PartialEq, Eq, Archive, Deserialize, Serialize)]
+#[archive(derive(CheckBytes))]
+#[derive(serde::Deserialize, serde::Serialize)]
+#[serde(rename_all = "lowercase")]
 pub enum FileTypeFilter {
     Dirs,
     Files,
@@ -153,6 +175,7 @@ impl PartialEq<&ArchivedFileTypeFilter> for FileTypeFilter {
 }
 
 #[derive(Clone, Debug, PartialEq, Archive, Deserialize, Serialize)]
+#[archive(derive(CheckBytes))]
 pub enum FilterKind {
     Exts(Vec<String>),
     FileType(FileTypeFilter),
@@ -168,6 +191,7 @@ impl Default for FilterKind {
 }
 
 #[derive(Clone, Debug, PartialEq, Archive, Deserialize, Serialize)]
+#[archive(derive(CheckBytes))]
 pub enum FiltersKind {
     One(FilterKind),
     Many(Vec<FilterKind>),
@@ -181,6 +205,9 @@ impl Default for FiltersKind {
 }
 
 #[derive(Clone, Debug, PartialEq, Archive, Deserialize, Serialize)]
+#[archive(derive(CheckBytes))]
+#[derive(serde::Deserialize, serde::Serialize)]
+#[serde(rename_all = "lowercase")]
 pub enum EncoderKind {
     Json,
     Url,
@@ -192,6 +219,7 @@ impl Valid for EncoderKind {
 }
 
 #[derive(Clone, Debug, PartialEq, Archive, Deserialize, Serialize)]
+#[archive(derive(CheckBytes))]
 pub enum ScanDirKind {
     Depth(u8),
     None,
@@ -203,17 +231,19 @@ impl Default for ScanDirKind {
     }
 }
 
-impl From<bool> for ScanDirKind {
-    fn from(switch: bool) -> ScanDirKind {
-        if switch {
-            ScanDirKind::Depth(u8::MAX)
-        } else {
-            ScanDirKind::None
+impl ScanDirKind {
+    /// `depth` comes from settings.toml, 0 turns scanning off like the switch does
+    pub fn from_switch(switch: bool, depth: u8) -> ScanDirKind {
+        match depth {
+            0 => ScanDirKind::None,
+            depth if switch => ScanDirKind::Depth(depth),
+            _ => ScanDirKind::None,
         }
     }
 }
 
 #[derive(Debug, Default, PartialEq, Archive, Deserialize, Serialize)]
+#[archive(derive(CheckBytes))]
 pub struct GeneratedCommand {
     pub bin: String,
     pub dfl_args: Option<Vec<String>>,
@@ -223,13 +253,33 @@ pub struct GeneratedCommand {
     pub permissions: PermissionsKind,
     pub query_which: bool,
     pub scan_dir: ScanDirKind,
+    pub tags: Option<Vec<String>>,
+    /// run without waiting for the command, e.g. GUI apps
+    pub detach: bool,
+    /// variables set for the command, `None` unsets one
+    pub env: Option<HashMap<String, Option<String>>>,
+    /// where the command runs, `~` being the home directory
+    pub cwd: Option<String>,
 }
 
 impl Valid for GeneratedCommand {
     const VALID: &'static [&'static str] = &["y", "yes", "true", "n", "no", "false"];
 }
 
-use crate::cli::types::AddCmd;
+#[cfg(test)]
+impl GeneratedCommand {
+    /// `bin` with `args` and everything else left at its default, for tests
+    pub fn running(bin: &str, args: &[&str]) -> Self {
+        Self {
+            bin: bin.into(),
+            dfl_args: Some(args.iter().map(|arg| arg.to_string()).collect())
+                .filter(|args: &Vec<String>| !args.is_empty()),
+            ..Default::default()
+        }
+    }
+}
+
+use crate::{cli::types::AddCmd, config::settings::CmdDefaults};
 impl GeneratedCommand {
     pub fn clone_from(other: &mut GeneratedCommand) -> Self {
         let GeneratedCommand {
@@ -241,6 +291,10 @@ impl GeneratedCommand {
             ref mut permissions,
             ref mut query_which,
             ref mut scan_dir,
+            ref mut tags,
+            ref mut detach,
+            ref mut env,
+            ref mut cwd,
         } = other;
 
         let new_encoder = encoder.as_ref().and_then(|enc| Some(enc.clone()));
@@ -253,6 +307,8 @@ impl GeneratedCommand {
         *query_which = false;
         let new_scan_dir = scan_dir.clone();
         *scan_dir = ScanDirKind::None;
+        let new_detach = *detach;
+        *detach = false;
 
         Self {
             bin: bin.drain(..).collect(),
@@ -267,6 +323,10 @@ impl GeneratedCommand {
             permissions: new_permissions,
             query_which: which,
             scan_dir: new_scan_dir,
+            tags: tags.take(),
+            detach: new_detach,
+            env: env.take(),
+            cwd: cwd.take(),
         }
     }
 
@@ -281,23 +341,46 @@ impl GeneratedCommand {
             query_which,
             scan_dir,
             filter,
+            mut tags,
+            detach,
+            env,
+            cwd,
             ..
         }: AddCmd,
+        defaults: &CmdDefaults,
     ) -> (String, GeneratedCommand) {
         (
             key,
             GeneratedCommand {
                 aliases,
                 bin,
-                encoder,
-                permissions: permissions.into(),
-                scan_dir: scan_dir.into(),
+                encoder: encoder
+                    .or_else(|| Some(defaults.encoder.clone()))
+                    .filter(|encoder| *encoder != EncoderKind::None),
+                permissions: if permissions {
+                    PermissionsKind::Root
+                } else {
+                    defaults.permissions.clone()
+                },
+                scan_dir: ScanDirKind::from_switch(scan_dir, defaults.scan_depth),
                 query_which,
                 dfl_args: if args.is_empty() { None } else { Some(args) },
                 filter: match filter {
                     Some(e) => FiltersKind::One(FilterKind::RegEx(e)),
                     None => FiltersKind::None,
                 },
+                tags: {
+                    tags.sort();
+                    tags.dedup();
+                    Some(tags).filter(|tags| !tags.is_empty())
+                },
+                detach,
+                env: if env.is_empty() {
+                    None
+                } else {
+                    Some(env.into_iter().collect())
+                },
+                cwd,
             },
         )
     }
@@ -1,25 +1,77 @@
-use crate::prelude::*;
+use super::{
+	backup::snapshot_current,
+	header::DbHeader,
+	journal::{self, Op},
+	lock::DbLock,
+	source,
+};
+use crate::{prelude::*, utils::fs::atomic::write_atomic};
 use rkyv::{
+	de::deserializers::AllocDeserializer,
 	ser::{serializers::WriteSerializer, Serializer},
-	AlignedVec,
+	AlignedVec, Deserialize,
 };
-use std::fs::{create_dir_all, write};
+use std::fs::{create_dir_all, read_to_string, remove_file};
 
-pub fn init_cmds_if_not_exists() -> Result<()> {
-	if !ConfigPath::Base.abs().exists() {
-		create_dir_all(ConfigPath::Base.abs())?;
-		overwrite_cmds(GeneratedCommands::default())?;
-	} else if !ConfigPath::Commands.abs().exists() {
-		overwrite_cmds(GeneratedCommands::default())?;
+pub fn init_cmds_if_not_exists(cfg: &ConfigDir) -> Result<()> {
+	if !ConfigPath::Commands.abs(cfg).exists() {
+		create_dir_all(ConfigPath::Data.abs(cfg))?;
+		overwrite_cmds(cfg, GeneratedCommands::default())?;
 	}
 	Ok(())
 }
 
-pub fn overwrite_cmds(new_cmds: GeneratedCommands) -> Result<()> {
+pub fn serialize_cmds(cmds: &GeneratedCommands) -> Result<Vec<u8>> {
 	let mut serializer = WriteSerializer::new(AlignedVec::new());
-	let pos = serializer.serialize_value(&new_cmds)?;
-	write(ConfigPath::Pos.abs(), pos.to_string())?;
-	let buf = serializer.into_inner();
-	write(ConfigPath::Commands.abs(), buf)?;
+	let pos = serializer.serialize_value(cmds)?;
+	let payload = serializer.into_inner();
+
+	let header = DbHeader::for_payload(&payload, pos);
+	let mut buf = Vec::with_capacity(DbHeader::SIZE + payload.len());
+	buf.extend_from_slice(&header.to_bytes());
+	buf.extend_from_slice(&payload);
+	Ok(buf)
+}
+
+pub fn overwrite_cmds(cfg: &ConfigDir, mut new_cmds: GeneratedCommands) -> Result<()> {
+	// lookups on empty archived maps divide by zero in rkyv 0.5
+	if new_cmds.commands.as_ref().is_some_and(HashMap::is_empty) {
+		new_cmds.commands = None;
+	}
+	if new_cmds.aliases.as_ref().is_some_and(HashMap::is_empty) {
+		new_cmds.aliases = None;
+	}
+	let bytes = serialize_cmds(&new_cmds)?;
+	let _lock = DbLock::acquire(cfg)?;
+	// legacy archives in commands.toml aren't text and get rendered from scratch
+	let toml = match read_to_string(ConfigPath::Commands.abs(cfg)) {
+		Ok(existing) => source::render_over(&existing, &new_cmds)?,
+		Err(_) => source::render(&new_cmds)?,
+	};
+	snapshot_current(cfg)?;
+	write_atomic(ConfigPath::Commands.abs(cfg), toml)?;
+	write_cache(cfg, bytes)?;
+	// the root position now lives in the header
+	if ConfigPath::Pos.abs(cfg).exists() {
+		remove_file(ConfigPath::Pos.abs(cfg))?;
+	}
 	Ok(())
 }
+
+/// overwrite_cmds for user facing mutations, journaling each changed command so it can be undone
+pub fn overwrite_journaled(cfg: &ConfigDir, op: Op, new_cmds: GeneratedCommands) -> Result<()> {
+	let _lock = DbLock::acquire(cfg)?;
+	// diffed against what's on disk so merged tui sessions only journal their own changes
+	let old = CmdsDb::from_cfg(cfg)?
+		.archive()
+		.deserialize(&mut AllocDeserializer)?;
+	let entries = journal::diff(op, &old, &new_cmds);
+	overwrite_cmds(cfg, new_cmds)?;
+	journal::append(cfg, entries)
+}
+
+/// replaces the compiled cache without touching commands.toml
+pub fn write_cache<B: AsRef<[u8]>>(cfg: &ConfigDir, bytes: B) -> Result<()> {
+	write_atomic(ConfigPath::Cache.abs(cfg), bytes)?;
+	source::mark_cache_fresh(cfg)
+}
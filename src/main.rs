@@ -8,8 +8,12 @@ pub(crate) mod utils;
 use prelude::*;
 
 fn main() -> Result<()> {
-    config::write::init_cmds_if_not_exists()?;
-
     let flurry_app: cli::types::Flurry = argh::from_env();
-    cli::exec_cli(flurry_app)
+    let root = ConfigDir::resolve(flurry_app.db.clone());
+
+    // the default set always exists, other profiles are created with `flurry profile create`
+    config::write::init_cmds_if_not_exists(&root)?;
+    let settings = config::settings::Settings::load(&root)?;
+    let profile = flurry_app.profile.clone().or_else(|| settings.profile.clone());
+    cli::exec_cli(flurry_app, &root.with_profile(profile), &settings)
 }
@@ -1,7 +1,7 @@
 // macros
-pub use super::{run_cmd, seppuku};
+pub use super::seppuku;
 // crate utils
-pub use super::config::{get::CmdsDb, types::*, ConfigPath};
+pub use super::config::{get::CmdsDb, types::*, ConfigDir, ConfigPath};
 pub use super::utils::traits::*;
 
 // std lib
@@ -89,7 +89,7 @@ impl StatefulCmdsTable<'_> {
         let mut terminal = Terminal::new(backend)?;
 
         // spawn the event loop
-        let rx = Event::spawn_loop(Event::POLL_RATE);
+        let rx = Event::spawn_loop(self.poll_rate());
         loop {
             if let Some(sender) = testing_poller {
                 sender.send(*ui_state).seppuku(None);
@@ -138,7 +138,9 @@ impl StatefulCmdsTable<'_> {
                                 continue;
                             }
                             let SeqFrame { query, ref buf, .. } = seq.current_frame();
-                            if let Err(e) = AddSeq::set_new_val(query, buf, &mut bufs_ref.cmd) {
+                            if let Err(e) =
+                                AddSeq::set_new_val(query, buf, self.defaults(), &mut bufs_ref.cmd)
+                            {
                                 seq.err_msg.replace(e);
                                 continue;
                             }
@@ -47,9 +47,14 @@ impl UiStack {
     pub const ALIASES: &'static str = "Aliases for command?";
     pub const ENCODER: &'static str = "Encoder for output?";
     pub const PERMISSIONS: &'static str = "Permissions schema?";
+    pub const NEW_ENCODER: &'static str = "Encoder for output? (empty for the default)";
+    pub const NEW_PERMISSIONS: &'static str = "Permissions schema? (empty for the default)";
     pub const SCAN_DIR: &'static str =
         "Scan directory? (Enter an integer to set fixed recursion limit)";
     pub const WHICH: &'static str = "Query which?";
+    pub const DETACH: &'static str = "Detach? (don't wait for GUI apps)";
+    pub const ENV: &'static str = "Environment? (KEY=value to set, -KEY to unset, space separated)";
+    pub const CWD: &'static str = "Working directory? (empty to run where flurry is called)";
 
     pub const KEY_ERR: &'static str = "key cannot be empty";
     pub const BIN_ERR: &'static str = "trigger cannot be empty";
@@ -59,6 +64,9 @@ impl UiStack {
     pub const PERMISSIONS_ERR: &'static str = "Permissions schema?";
     pub const SCAN_DIR_ERR: &'static str = "valid values: max, recursive, none, {int} (max 255)";
     pub const WHICH_ERR: &'static str = "(y)es or (n)o";
+    pub const DETACH_ERR: &'static str = "(y)es or (n)o";
+    pub const ENV_ERR: &'static str = "KEY=value or -KEY, names are letters, digits and _";
+    pub const CWD_ERR: &'static str = "";
 }
 
 #[derive(Debug)]
@@ -1,10 +1,11 @@
 use crate::{
+    config::settings::CmdDefaults,
     prelude::*,
     tui::{layout::*, widgets::*},
     utils::parse::*,
 };
 
-pub const ADD_SEQ_NUM_FRAMES: usize = 8;
+pub const ADD_SEQ_NUM_FRAMES: usize = 11;
 
 pub struct AddSeq;
 impl AddSeq {
@@ -12,10 +13,13 @@ impl AddSeq {
     pub const BIN: &'static str = UiStack::BIN;
     pub const ARGS: &'static str = UiStack::ARGS;
     pub const ALIASES: &'static str = UiStack::ALIASES;
-    pub const ENCODER: &'static str = UiStack::ENCODER;
-    pub const PERMISSIONS: &'static str = UiStack::PERMISSIONS;
+    pub const ENCODER: &'static str = UiStack::NEW_ENCODER;
+    pub const PERMISSIONS: &'static str = UiStack::NEW_PERMISSIONS;
     pub const SCAN_DIR: &'static str = UiStack::SCAN_DIR;
     pub const WHICH: &'static str = UiStack::WHICH;
+    pub const DETACH: &'static str = UiStack::DETACH;
+    pub const ENV: &'static str = UiStack::ENV;
+    pub const CWD: &'static str = UiStack::CWD;
 
     const KEY_ERR: &'static str = UiStack::KEY_ERR;
     const BIN_ERR: &'static str = UiStack::BIN_ERR;
@@ -25,10 +29,14 @@ impl AddSeq {
     const PERMISSIONS_ERR: &'static str = UiStack::PERMISSIONS_ERR;
     const SCAN_DIR_ERR: &'static str = UiStack::SCAN_DIR_ERR;
     const WHICH_ERR: &'static str = UiStack::WHICH_ERR;
+    const DETACH_ERR: &'static str = UiStack::DETACH_ERR;
+    const ENV_ERR: &'static str = UiStack::ENV_ERR;
+    const CWD_ERR: &'static str = UiStack::CWD_ERR;
 
     pub fn set_new_val(
         key: &str,
         new_val: &String,
+        defaults: &CmdDefaults,
         GeneratedCommand {
             bin,
             dfl_args,
@@ -37,6 +45,9 @@ impl AddSeq {
             permissions,
             query_which,
             scan_dir,
+            detach,
+            env,
+            cwd,
             ..
         }: &mut GeneratedCommand,
     ) -> Result<(), String> {
@@ -54,13 +65,24 @@ impl AddSeq {
                 aliases.replace(aliases_from_arg(new_val.as_str())?);
             }
             Self::ENCODER => {
-                encoder.replace(encoder_from_arg(new_val.as_str())?);
+                let kind = match new_val.trim() {
+                    "" => defaults.encoder.clone(),
+                    val => encoder_from_arg(val)?,
+                };
+                *encoder = Some(kind).filter(|kind| *kind != EncoderKind::None);
             }
             Self::PERMISSIONS => {
-                *permissions = permissions_from_arg(&new_val.as_str())?;
+                *permissions = match new_val.trim() {
+                    "" => defaults.permissions.clone(),
+                    val => permissions_from_arg(val)?,
+                };
             }
             Self::SCAN_DIR => {
-                *scan_dir = recursion_limit_from_arg(new_val.as_str())?;
+                *scan_dir = match new_val.to_lowercase().trim() {
+                    "y" | "yes" | "true" => ScanDirKind::from_switch(true, defaults.scan_depth),
+                    "n" | "no" | "false" => ScanDirKind::None,
+                    depth => recursion_limit_from_arg(depth)?,
+                }
             }
             Self::WHICH => {
                 *query_which = match new_val.to_lowercase().trim() {
@@ -68,6 +90,17 @@ impl AddSeq {
                     _ => false,
                 }
             }
+            Self::DETACH => {
+                *detach = matches!(new_val.to_lowercase().trim(), "y" | "yes" | "true");
+            }
+            Self::ENV => {
+                let vars: HashMap<_, _> =
+                    env_list_from_arg(new_val.as_str())?.into_iter().collect();
+                *env = Some(vars).filter(|vars| !vars.is_empty());
+            }
+            Self::CWD => {
+                *cwd = Some(new_val.trim().to_owned()).filter(|cwd| !cwd.is_empty());
+            }
             _ => return Err("not a valid key".into()),
         }
         Ok(())
@@ -80,18 +113,27 @@ pub fn add_seq_items() -> [SeqFrame; ADD_SEQ_NUM_FRAMES] {
         SeqFrame::new(AddSeq::BIN, AddSeq::BIN_ERR, |val| !val.trim().is_empty()),
         SeqFrame::new(AddSeq::ARGS, AddSeq::ARGS_ERR, |_| true),
         SeqFrame::new(AddSeq::ALIASES, AddSeq::ALIASES_ERR, |_| true),
-        SeqFrame::new(AddSeq::ENCODER, AddSeq::ENCODER_ERR, EncoderKind::is_valid),
-        SeqFrame::new(
-            AddSeq::PERMISSIONS,
-            AddSeq::PERMISSIONS_ERR,
-            PermissionsKind::is_valid,
-        ),
+        SeqFrame::new(AddSeq::ENCODER, AddSeq::ENCODER_ERR, |val| {
+            val.trim().is_empty() || EncoderKind::is_valid(val)
+        }),
+        SeqFrame::new(AddSeq::PERMISSIONS, AddSeq::PERMISSIONS_ERR, |val| {
+            val.trim().is_empty() || PermissionsKind::is_valid(val)
+        }),
         SeqFrame::new(
             AddSeq::SCAN_DIR,
             AddSeq::SCAN_DIR_ERR,
             GeneratedCommand::is_valid,
         ),
         SeqFrame::new(AddSeq::WHICH, AddSeq::WHICH_ERR, GeneratedCommand::is_valid),
+        SeqFrame::new(
+            AddSeq::DETACH,
+            AddSeq::DETACH_ERR,
+            GeneratedCommand::is_valid,
+        ),
+        SeqFrame::new(AddSeq::ENV, AddSeq::ENV_ERR, |val| {
+            env_list_from_arg(val).is_ok()
+        }),
+        SeqFrame::new(AddSeq::CWD, AddSeq::CWD_ERR, |_| true),
     ]
 }
 
@@ -1,6 +1,6 @@
 use crate::{prelude::*, tui::widgets::*, utils::parse::*};
 
-pub const EDIT_SEQ_NUM_FRAMES: usize = 7;
+pub const EDIT_SEQ_NUM_FRAMES: usize = 10;
 
 pub struct EditSeq;
 impl EditSeq {
@@ -11,6 +11,9 @@ impl EditSeq {
     const PERMISSIONS: &'static str = UiStack::PERMISSIONS;
     const SCAN_DIR: &'static str = UiStack::SCAN_DIR;
     const WHICH: &'static str = UiStack::WHICH;
+    const DETACH: &'static str = UiStack::DETACH;
+    const ENV: &'static str = UiStack::ENV;
+    const CWD: &'static str = UiStack::CWD;
 
     const BIN_ERR: &'static str = UiStack::BIN_ERR;
     const ARGS_ERR: &'static str = UiStack::ARGS_ERR;
@@ -18,6 +21,9 @@ impl EditSeq {
     const ENCODER_ERR: &'static str = UiStack::ENCODER_ERR;
     const PERMISSIONS_ERR: &'static str = UiStack::PERMISSIONS_ERR;
     const WHICH_ERR: &'static str = UiStack::WHICH_ERR;
+    const DETACH_ERR: &'static str = UiStack::DETACH_ERR;
+    const ENV_ERR: &'static str = UiStack::ENV_ERR;
+    const CWD_ERR: &'static str = UiStack::CWD_ERR;
 
     pub fn set_new_val(
         key: &str,
@@ -30,6 +36,9 @@ impl EditSeq {
             permissions,
             query_which,
             scan_dir,
+            detach,
+            env,
+            cwd,
             ..
         }: &mut GeneratedCommand,
     ) -> Result<(), String> {
@@ -61,6 +70,17 @@ impl EditSeq {
                     _ => false,
                 }
             }
+            Self::DETACH => {
+                *detach = matches!(new_val.to_lowercase().trim(), "y" | "yes" | "true");
+            }
+            Self::ENV => {
+                let vars: HashMap<_, _> =
+                    env_list_from_arg(new_val.as_str())?.into_iter().collect();
+                *env = Some(vars).filter(|vars| !vars.is_empty());
+            }
+            Self::CWD => {
+                *cwd = Some(new_val.trim().to_owned()).filter(|cwd| !cwd.is_empty());
+            }
             _ => return Err("not a valid key".into()),
         }
         Ok(())
@@ -90,5 +110,14 @@ pub fn edit_seq_items() -> [SeqFrame; EDIT_SEQ_NUM_FRAMES] {
             EditSeq::WHICH_ERR,
             GeneratedCommand::is_valid,
         ),
+        SeqFrame::new(
+            EditSeq::DETACH,
+            EditSeq::DETACH_ERR,
+            GeneratedCommand::is_valid,
+        ),
+        SeqFrame::new(EditSeq::ENV, EditSeq::ENV_ERR, |val| {
+            env_list_from_arg(val).is_ok()
+        }),
+        SeqFrame::new(EditSeq::CWD, EditSeq::CWD_ERR, |_| true),
     ]
 }
@@ -1,4 +1,4 @@
-use crate::prelude::*;
+use crate::{config::settings::CmdDefaults, prelude::*, tui::runtime::events::Event};
 
 use std::{array, cell::RefCell};
 use tui::widgets::TableState;
@@ -14,6 +14,8 @@ pub struct StatefulCmdsTable<'cmds> {
     header_style: Style,
     selection_style: Style,
     rm_selection_style: Style,
+    poll_rate: u64,
+    defaults: CmdDefaults,
 }
 
 use tui::{
@@ -41,6 +43,8 @@ impl<'cmds> StatefulCmdsTable<'cmds> {
             header_style: Style::default(),
             selection_style: Style::default(),
             rm_selection_style: Style::default(),
+            poll_rate: Event::POLL_RATE,
+            defaults: CmdDefaults::default(),
         }
     }
 
@@ -59,6 +63,26 @@ impl<'cmds> StatefulCmdsTable<'cmds> {
         self
     }
 
+    pub fn with_poll_rate(mut self, millis: u64) -> Self {
+        self.poll_rate = millis;
+        self
+    }
+
+    /// used by commands added in the tui for the answers left empty, and as the depth scanned
+    /// when scan_dir is answered yes
+    pub fn with_defaults(mut self, defaults: CmdDefaults) -> Self {
+        self.defaults = defaults;
+        self
+    }
+
+    pub fn poll_rate(&self) -> u64 {
+        self.poll_rate
+    }
+
+    pub fn defaults(&self) -> &CmdDefaults {
+        &self.defaults
+    }
+
     pub fn update_cache(&mut self) {
         let borrowed_cmds = self.cmds.borrow();
         self.key_cache = borrowed_cmds.keys().enumerate().fold(
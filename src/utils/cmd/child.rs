@@ -0,0 +1,152 @@
+//! running commands the way a shell would: in the foreground with flurry waiting on them and
+//! exiting with their status, or detached from the terminal for GUI apps
+
+use crate::prelude::*;
+use std::{
+    io::{ErrorKind, Write},
+    process::{Child, Command, ExitStatus, Stdio},
+};
+
+/// spawns `cmd`, writing `stdin` to it when there is a payload, and waits for it to finish
+pub fn run_foreground(mut cmd: Command, stdin: Option<String>) -> Result<i32> {
+    if stdin.is_some() {
+        cmd.stdin(Stdio::piped());
+    }
+    let mut child = cmd.spawn()?;
+    let relay = Relay::install(&child);
+    feed(&mut child, stdin)?;
+    let status = child.wait()?;
+    drop(relay);
+    Ok(exit_code(status))
+}
+
+/// spawns `cmd` without a terminal and returns right away
+pub fn run_detached(mut cmd: Command, stdin: Option<String>) -> Result<()> {
+    cmd.stdin(if stdin.is_some() {
+        Stdio::piped()
+    } else {
+        Stdio::null()
+    })
+    .stdout(Stdio::null())
+    .stderr(Stdio::null());
+    #[cfg(unix)]
+    {
+        use std::os::unix::process::CommandExt;
+        // a session of its own so closing the terminal doesn't take the app down with it
+        unsafe {
+            cmd.pre_exec(|| {
+                libc::setsid();
+                Ok(())
+            });
+        }
+    }
+    let mut child = cmd.spawn()?;
+    feed(&mut child, stdin)
+}
+
+/// the pipe is closed once written so the command sees the end of its input, commands that
+/// exit without reading all of it aren't an error
+fn feed(child: &mut Child, stdin: Option<String>) -> Result<()> {
+    if let (Some(payload), Some(mut pipe)) = (stdin, child.stdin.take()) {
+        match pipe.write_all(payload.as_bytes()) {
+            Err(e) if e.kind() != ErrorKind::BrokenPipe => return Err(e.into()),
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+/// what a shell would report, 128 + the signal for commands killed by one
+fn exit_code(status: ExitStatus) -> i32 {
+    #[cfg(unix)]
+    {
+        use std::os::unix::process::ExitStatusExt;
+        if let Some(signal) = status.signal() {
+            return 128 + signal;
+        }
+    }
+    status.code().unwrap_or(1)
+}
+
+/// relays signals sent to flurry to the command while it runs. Ctrl-C and friends already reach
+/// the whole foreground process group, so only signals sent with kill(2) are passed on and
+/// the terminal's ones are just kept from killing flurry before the command exits.
+#[cfg(unix)]
+struct Relay {
+    previous: Vec<(libc::c_int, libc::sigaction)>,
+}
+
+#[cfg(unix)]
+static CHILD: std::sync::atomic::AtomicI32 = std::sync::atomic::AtomicI32::new(0);
+
+#[cfg(unix)]
+extern "C" fn relay(signal: libc::c_int, info: *mut libc::siginfo_t, _: *mut libc::c_void) {
+    use std::sync::atomic::Ordering;
+
+    let child = CHILD.load(Ordering::SeqCst);
+    // SI_USER and below are kill(2), sigqueue(3) and friends, the terminal signals as the kernel
+    if child > 0 && !info.is_null() && unsafe { (*info).si_code } <= libc::SI_USER {
+        unsafe {
+            libc::kill(child, signal);
+        }
+    }
+}
+
+#[cfg(unix)]
+impl Relay {
+    const SIGNALS: [libc::c_int; 4] = [libc::SIGINT, libc::SIGTERM, libc::SIGHUP, libc::SIGQUIT];
+
+    fn install(child: &Child) -> Self {
+        use std::{mem::zeroed, sync::atomic::Ordering};
+
+        CHILD.store(child.id() as i32, Ordering::SeqCst);
+        let mut previous = vec![];
+        for &signal in Self::SIGNALS.iter() {
+            unsafe {
+                let mut action: libc::sigaction = zeroed();
+                let handler: extern "C" fn(_, _, _) = relay;
+                action.sa_sigaction = handler as libc::sighandler_t;
+                action.sa_flags = libc::SA_SIGINFO | libc::SA_RESTART;
+                libc::sigemptyset(&mut action.sa_mask);
+                let mut old: libc::sigaction = zeroed();
+                if libc::sigaction(signal, &action, &mut old) == 0 {
+                    previous.push((signal, old));
+                }
+            }
+        }
+        Self { previous }
+    }
+}
+
+#[cfg(unix)]
+impl Drop for Relay {
+    fn drop(&mut self) {
+        for (signal, old) in &self.previous {
+            unsafe {
+                libc::sigaction(*signal, old, std::ptr::null_mut());
+            }
+        }
+        CHILD.store(0, std::sync::atomic::Ordering::SeqCst);
+    }
+}
+
+#[cfg(not(unix))]
+struct Relay;
+
+#[cfg(not(unix))]
+impl Relay {
+    fn install(_: &Child) -> Self {
+        Relay
+    }
+}
+
+#[test]
+fn reports_exit_status() {
+    let mut failing = Command::new("sh");
+    failing.args(["-c", "exit 3"]);
+    assert_eq!(run_foreground(failing, None).unwrap(), 3);
+
+    let mut reading = Command::new("sh");
+    reading.args(["-c", r#"test "$(cat)" = '["a"]'"#]);
+    assert_eq!(run_foreground(reading, Some(r#"["a"]"#.into())).unwrap(), 0);
+}
@@ -1,5 +1,13 @@
+pub mod child;
+pub mod template;
+
 use crate::prelude::*;
-use std::{ffi::OsStr, path::PathBuf};
+use std::{
+    ffi::{OsStr, OsString},
+    path::PathBuf,
+    process::Command,
+};
+use template::{expand, Context, Expanded};
 
 enum BinKind<'bin> {
     Borrowed(&'bin str),
@@ -15,6 +23,101 @@ impl AsRef<OsStr> for BinKind<'_> {
     }
 }
 
+/// what running a command comes down to once it's resolved, see `go --dry-run`
+pub struct Plan {
+    pub bin: PathBuf,
+    pub args: Vec<OsString>,
+    /// the JSON payload written to the command's stdin
+    pub stdin: Option<String>,
+    /// files matched by the directory scan, for commands that have one
+    pub files: Option<usize>,
+    pub detach: bool,
+    pub root: bool,
+    /// variables set for the command, sorted by name, `None` unsets one
+    pub env: Vec<(String, Option<String>)>,
+    /// `None` runs the command where flurry is called. Scanned files are made absolute and
+    /// `{cwd}` stays the caller's directory when it's set.
+    pub cwd: Option<PathBuf>,
+}
+
+/// `~` and `~/...` start at the home directory, anything else is taken as it is
+fn working_dir(cwd: &str) -> Result<PathBuf> {
+    let dir = match cwd.strip_prefix('~') {
+        Some("") => crate::utils::os::home(),
+        Some(rest) if rest.starts_with('/') => crate::utils::os::home().join(&rest[1..]),
+        _ => PathBuf::from(cwd),
+    };
+    if !dir.is_dir() {
+        bail!("The working directory {} doesn't exist", dir.display());
+    }
+    Ok(dir)
+}
+
+fn sorted_env<'a, I: Iterator<Item = (&'a str, Option<&'a str>)>>(
+    vars: I,
+) -> Vec<(String, Option<String>)> {
+    let mut env: Vec<(String, Option<String>)> = vars
+        .map(|(key, value)| (key.to_owned(), value.map(str::to_owned)))
+        .collect();
+    env.sort();
+    env
+}
+
+impl Plan {
+    /// `bin` with its stored args filled in the same way `go` fills them, for the tui which has
+    /// no user args, and so no directories to scan either
+    pub fn stored(cmd: &GeneratedCommand) -> Result<Plan> {
+        let files = match cmd.scan_dir {
+            ScanDirKind::Depth(_) => Some(&[][..]),
+            _ => None,
+        };
+        let Expanded { args, stdin } = expand(
+            cmd.dfl_args.as_deref().unwrap_or_default(),
+            &Context {
+                args: &[],
+                files,
+                encoder: cmd.encoder.clone().unwrap_or(EncoderKind::None),
+            },
+        )?;
+        Ok(Plan {
+            bin: PathBuf::from(&cmd.bin),
+            args,
+            stdin,
+            files: files.map(<[PathBuf]>::len),
+            detach: cmd.detach,
+            root: cmd.permissions == PermissionsKind::Root,
+            env: sorted_env(
+                cmd.env
+                    .iter()
+                    .flatten()
+                    .map(|(key, value)| (key.as_str(), value.as_deref())),
+            ),
+            cwd: cmd.cwd.as_deref().map(working_dir).transpose()?,
+        })
+    }
+
+    /// the command's exit code, 0 for detached commands
+    pub fn run(self) -> Result<i32> {
+        let mut cmd = Command::new(&self.bin);
+        cmd.args(&self.args);
+        for (key, value) in &self.env {
+            match value {
+                Some(value) => cmd.env(key, value),
+                None => cmd.env_remove(key),
+            };
+        }
+        if let Some(cwd) = &self.cwd {
+            cmd.current_dir(cwd);
+        }
+        if self.detach {
+            child::run_detached(cmd, self.stdin)?;
+            Ok(0)
+        } else {
+            child::run_foreground(cmd, self.stdin)
+        }
+    }
+}
+
 use crate::{
     cli::types::GoCmd,
     utils::{fs::recursive::*, os::ensure_root},
@@ -23,38 +126,92 @@ use crate::{
 use rkyv::core_impl::ArchivedOption;
 
 impl ArchivedGeneratedCommand {
-    fn get_bin(&self) -> BinKind {
+    fn get_bin(&self) -> Result<BinKind<'_>> {
         if !self.query_which {
-            BinKind::Borrowed(self.bin.as_ref())
+            Ok(BinKind::Borrowed(self.bin.as_ref()))
         } else {
             if let Ok(bin) = which::which(self.bin.as_ref()) {
-                return BinKind::Whiched(bin);
+                return Ok(BinKind::Whiched(bin));
             }
 
             if let ArchivedOption::Some(aliases) = &self.aliases {
                 for alias in aliases.iter() {
                     if let Ok(bin) = which::which(alias.as_ref()) {
-                        return BinKind::Whiched(bin);
+                        return Ok(BinKind::Whiched(bin));
                     }
                 }
             }
 
-            seppuku!("Could not find a path for any alias");
+            bail!("Could not find a path for `{}` or any alias", self.bin)
         }
     }
 
-    pub fn try_exec(&self, GoCmd { args, random, .. }: &GoCmd) -> Result<()> {
-        let ArchivedGeneratedCommand {
-            dfl_args,
-            permissions,
-            filter,
-            scan_dir,
-            ..
-        } = self;
+    /// the bin with `dfl_args` expanded and the payload encoded, see `template`
+    fn plan_with(&self, args: &[String], files: Option<&[PathBuf]>, detach: bool) -> Result<Plan> {
+        let dfl_args: Vec<&str> = match &self.dfl_args {
+            ArchivedOption::Some(dfl) => dfl.iter().map(|a| a.as_ref()).collect(),
+            ArchivedOption::None => vec![],
+        };
+        let encoder = match &self.encoder {
+            ArchivedOption::Some(ArchivedEncoderKind::Url) => EncoderKind::Url,
+            ArchivedOption::Some(ArchivedEncoderKind::Json) => EncoderKind::Json,
+            _ => EncoderKind::None,
+        };
+        let Expanded { args, stdin } = expand(
+            &dfl_args,
+            &Context {
+                args,
+                files,
+                encoder,
+            },
+        )?;
 
-        if let ArchivedPermissionsKind::Root = permissions {
+        Ok(Plan {
+            bin: PathBuf::from(self.get_bin()?.as_ref()),
+            args,
+            stdin,
+            files: files.map(<[PathBuf]>::len),
+            detach,
+            root: matches!(self.permissions, ArchivedPermissionsKind::Root),
+            env: match &self.env {
+                ArchivedOption::Some(vars) => sorted_env(vars.iter().map(|(key, value)| {
+                    let value = match value {
+                        ArchivedOption::Some(value) => Some(value.as_str()),
+                        ArchivedOption::None => None,
+                    };
+                    (key.as_str(), value)
+                })),
+                ArchivedOption::None => vec![],
+            },
+            cwd: match &self.cwd {
+                ArchivedOption::Some(cwd) => Some(working_dir(cwd)?),
+                ArchivedOption::None => None,
+            },
+        })
+    }
+
+    pub fn try_exec(&self, go: &GoCmd) -> Result<i32> {
+        if let ArchivedPermissionsKind::Root = self.permissions {
             ensure_root();
         }
+        self.plan(go)?.run()
+    }
+
+    /// scans directories and fills in placeholders without running anything
+    pub fn plan(
+        &self,
+        GoCmd {
+            args,
+            random,
+            detach,
+            ..
+        }: &GoCmd,
+    ) -> Result<Plan> {
+        let ArchivedGeneratedCommand {
+            filter, scan_dir, ..
+        } = self;
+        let detach = *detach || self.detach;
+
         if let ArchivedScanDirKind::Depth(depth) = scan_dir {
             let files_list =
                 args.iter()
@@ -106,22 +263,85 @@ impl ArchivedGeneratedCommand {
                         }
                         res
                     })?;
-
-            match &dfl_args {
-                ArchivedOption::Some(dfl) => {
-                    run_cmd!(@ self.get_bin() => dfl.iter().map(|a| a.as_ref()), files_list)
+            // found relative to where flurry was called, which isn't where the command runs
+            let files_list = match self.cwd {
+                ArchivedOption::Some(_) => {
+                    let caller = std::env::current_dir()?;
+                    files_list.into_iter().map(|file| caller.join(file)).collect()
                 }
-                ArchivedOption::None => run_cmd!(@ self.get_bin() => files_list),
-            }?;
+                ArchivedOption::None => files_list,
+            };
+
+            self.plan_with(args, Some(&files_list), detach)
         } else {
-            match &dfl_args {
-                ArchivedOption::Some(dfl) => {
-                    run_cmd!(@ self.get_bin() => dfl.iter().map(|a| a.as_ref()), args)
-                }
-                ArchivedOption::None => run_cmd!(@ self.get_bin() => args),
-            }?;
+            self.plan_with(args, None, detach)
         }
-
-        Ok(())
     }
 }
+
+#[test]
+fn paths_stay_relative_to_the_caller() {
+    let caller = std::env::current_dir().unwrap();
+    let mut commands = HashMap::new();
+    commands.insert(
+        "here".to_owned(),
+        GeneratedCommand {
+            bin: "echo".into(),
+            dfl_args: Some(vec!["{cwd}".into()]),
+            cwd: Some("/".into()),
+            ..Default::default()
+        },
+    );
+    commands.insert(
+        "scan".to_owned(),
+        GeneratedCommand {
+            bin: "echo".into(),
+            scan_dir: ScanDirKind::Depth(1),
+            cwd: Some("/".into()),
+            ..Default::default()
+        },
+    );
+    let db = crate::config::get::CmdsDb::from_cmds(&GeneratedCommands {
+        commands: Some(commands),
+        aliases: None,
+    })
+    .unwrap();
+    let go = |command: &str, args: &[&str]| GoCmd {
+        command: command.into(),
+        random: false,
+        detach: false,
+        dry_run: true,
+        json: false,
+        args: args.iter().map(|arg| arg.to_string()).collect(),
+    };
+
+    let here = db.archive().get("here").unwrap().plan(&go("here", &[])).unwrap();
+    assert_eq!(here.cwd, Some(PathBuf::from("/")));
+    assert_eq!(here.args, vec![OsString::from(&caller)]);
+
+    let scan = db.archive().get("scan").unwrap().plan(&go("scan", &["src"])).unwrap();
+    assert!(!scan.args.is_empty());
+    assert!(scan
+        .args
+        .iter()
+        .all(|file| PathBuf::from(file).starts_with(caller.join("src"))));
+}
+
+#[test]
+fn stored_commands_fill_placeholders() {
+    std::env::set_var("FLURRY_TEST_DEST", "/tmp");
+    let cmd = |dfl_args: &[&str], encoder: Option<EncoderKind>| GeneratedCommand {
+        encoder,
+        ..GeneratedCommand::running("echo", dfl_args)
+    };
+
+    let plan = Plan::stored(&cmd(&["{@}", "{env:FLURRY_TEST_DEST}", "{home}"], None)).unwrap();
+    let home = crate::utils::os::home().into_os_string();
+    assert_eq!(plan.args, vec![OsString::from("/tmp"), home]);
+
+    // json commands get their (empty) payload on stdin
+    let json = Plan::stored(&cmd(&[], Some(EncoderKind::Json))).unwrap();
+    assert_eq!(json.stdin.as_deref(), Some("[]"));
+
+    assert!(Plan::stored(&cmd(&["{1}"], None)).is_err());
+}
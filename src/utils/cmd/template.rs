@@ -0,0 +1,346 @@
+//! placeholders in stored args, filled in when the command runs:
+//!
+//! - `{1}`, `{2}`... the user args by position
+//! - `{@}` every user arg
+//! - `{q}` the user args joined into one query
+//! - `{files}` the files found by a directory scan
+//! - `{cwd}` the directory flurry is called from, even for commands that run in a `cwd` of
+//!   their own
+//! - `{home}` and `{env:VAR}`
+//!
+//! `{@}` and `{files}` standing alone expand to one arg per value, inside a longer arg they're
+//! joined by spaces. Anything else in braces, like find's `{}`, is left as it is. User args and
+//! scanned files are still appended to commands that don't place them.
+//!
+//! The command's encoder then shapes the payload. `url` percent-encodes the user args, so
+//! `https://duckduckgo.com/?q={q}` searches for everything typed after the key. `json` fills
+//! `{N}` and `{q}` with JSON strings and `{@}` and `{files}` with a single JSON array, which is
+//! written to the command's stdin instead when the template doesn't place it.
+
+use crate::prelude::*;
+use percent_encoding::{utf8_percent_encode, AsciiSet, NON_ALPHANUMERIC};
+use regex::{Captures, Regex};
+use serde::Serialize;
+use std::{env, ffi::OsString, path::PathBuf, sync::OnceLock};
+
+/// everything but the unreserved characters of RFC 3986, safe anywhere in a query
+const COMPONENT: &AsciiSet = &NON_ALPHANUMERIC
+    .remove(b'-')
+    .remove(b'.')
+    .remove(b'_')
+    .remove(b'~');
+
+/// compiled once, shell exports check every arg of every command
+fn placeholder() -> &'static Regex {
+    static PLACEHOLDER: OnceLock<Regex> = OnceLock::new();
+    PLACEHOLDER.get_or_init(|| {
+        Regex::new(r"\{(\d+|@|q|files|cwd|home|env:[A-Za-z_][A-Za-z0-9_]*)\}").expect("valid regex")
+    })
+}
+
+pub fn has_placeholders(arg: &str) -> bool {
+    placeholder().is_match(arg)
+}
+
+/// what placeholders are filled in with
+pub struct Context<'a> {
+    pub args: &'a [String],
+    /// files found by the directory scan, for commands that have one
+    pub files: Option<&'a [PathBuf]>,
+    pub encoder: EncoderKind,
+}
+
+/// what the command is run with
+pub struct Expanded {
+    pub args: Vec<OsString>,
+    /// the JSON payload of `json` commands that don't place it
+    pub stdin: Option<String>,
+}
+
+fn arguments(n: usize) -> String {
+    match n {
+        1 => "1 argument".into(),
+        n => format!("{} arguments", n),
+    }
+}
+
+fn json<T: Serialize + ?Sized>(value: &T) -> String {
+    serde_json::to_string(value).expect("strings serialize")
+}
+
+#[derive(Default)]
+struct Usage {
+    /// highest `{n}`
+    max_position: usize,
+    all_args: bool,
+    files: bool,
+}
+
+impl Context<'_> {
+    fn encode(&self, value: &str) -> String {
+        match self.encoder {
+            EncoderKind::Url => utf8_percent_encode(value, COMPONENT).to_string(),
+            EncoderKind::Json => json(value),
+            EncoderKind::None => value.to_owned(),
+        }
+    }
+
+    fn query(&self) -> String {
+        self.encode(&self.args.join(" "))
+    }
+
+    /// every user arg as part of a single arg
+    fn joined_args(&self) -> String {
+        match self.encoder {
+            EncoderKind::Json => json(self.args),
+            _ => self.query(),
+        }
+    }
+
+    /// every user arg as args of their own, json keeps them together as one array
+    fn spliced_args(&self) -> Vec<OsString> {
+        match self.encoder {
+            EncoderKind::Json => vec![self.joined_args().into()],
+            _ => self
+                .args
+                .iter()
+                .map(|arg| self.encode(arg).into())
+                .collect(),
+        }
+    }
+
+    fn scanned(&self) -> Result<&[PathBuf]> {
+        self.files
+            .ok_or_else(|| anyhow!("`{{files}}` only applies to commands that scan directories"))
+    }
+
+    fn joined_files(&self) -> Result<String> {
+        let files: Vec<_> = self
+            .scanned()?
+            .iter()
+            .map(|file| file.to_string_lossy())
+            .collect();
+        Ok(match self.encoder {
+            EncoderKind::Json => json(&files),
+            _ => files.join(" "),
+        })
+    }
+
+    /// paths aren't user args, so only json changes them
+    fn spliced_files(&self) -> Result<Vec<OsString>> {
+        Ok(match self.encoder {
+            EncoderKind::Json => vec![self.joined_files()?.into()],
+            _ => self.scanned()?.iter().map(OsString::from).collect(),
+        })
+    }
+
+    fn fill(&self, name: &str, usage: &mut Usage) -> Result<String> {
+        Ok(match name {
+            "@" => {
+                usage.all_args = true;
+                self.joined_args()
+            }
+            "q" => {
+                usage.all_args = true;
+                self.query()
+            }
+            "files" => {
+                usage.files = true;
+                self.joined_files()?
+            }
+            "cwd" => env::current_dir()?.to_string_lossy().into_owned(),
+            "home" => crate::utils::os::home().to_string_lossy().into_owned(),
+            name => match name.strip_prefix("env:") {
+                Some(var) => env::var(var)
+                    .map_err(|_| anyhow!("`{{env:{}}}` is used but ${} isn't set", var, var))?,
+                None => {
+                    // positions too large for a usize can't have an argument either
+                    let arg = match name.parse::<usize>() {
+                        Ok(0) => bail!("`{{0}}` isn't an argument, positions start at `{{1}}`"),
+                        Ok(position) => {
+                            usage.max_position = usage.max_position.max(position);
+                            self.args.get(position - 1)
+                        }
+                        Err(_) => None,
+                    };
+                    match arg {
+                        Some(arg) => self.encode(arg),
+                        None => bail!(
+                            "No argument for `{{{}}}`, got {}",
+                            name,
+                            arguments(self.args.len())
+                        ),
+                    }
+                }
+            },
+        })
+    }
+}
+
+/// `dfl_args` with their placeholders filled in, followed by the user args or scanned files
+/// when the template doesn't place them
+pub fn expand<S: AsRef<str>>(dfl_args: &[S], ctx: &Context) -> Result<Expanded> {
+    let placeholder = placeholder();
+    let mut usage = Usage::default();
+    let mut expanded = vec![];
+
+    for arg in dfl_args.iter().map(AsRef::as_ref) {
+        match arg {
+            "{@}" => {
+                usage.all_args = true;
+                expanded.extend(ctx.spliced_args());
+            }
+            "{files}" => {
+                usage.files = true;
+                expanded.extend(ctx.spliced_files()?);
+            }
+            arg => {
+                let mut error = None;
+                let filled = placeholder.replace_all(arg, |caps: &Captures| {
+                    ctx.fill(&caps[1], &mut usage).unwrap_or_else(|e| {
+                        error.get_or_insert(e);
+                        String::new()
+                    })
+                });
+                if let Some(e) = error {
+                    return Err(e);
+                }
+                expanded.push(filled.into_owned().into());
+            }
+        }
+    }
+
+    let places_args = usage.all_args || usage.max_position > 0;
+    if places_args && !usage.all_args && ctx.args.len() > usage.max_position {
+        bail!(
+            "Takes {}, got {}",
+            arguments(usage.max_position),
+            ctx.args.len()
+        );
+    }
+    let json = ctx.encoder == EncoderKind::Json;
+    let mut stdin = None;
+    match ctx.files {
+        Some(_) if !usage.files && json => stdin = Some(ctx.joined_files()?),
+        Some(_) if !usage.files => expanded.extend(ctx.spliced_files()?),
+        None if !places_args && json => stdin = Some(ctx.joined_args()),
+        None if !places_args => expanded.extend(ctx.spliced_args()),
+        _ => {}
+    }
+    Ok(Expanded {
+        args: expanded,
+        stdin,
+    })
+}
+
+#[test]
+fn fills_placeholders() {
+    let args = vec!["in.mkv".to_owned(), "out.mp4".to_owned()];
+    let ctx = Context {
+        args: &args,
+        files: None,
+        encoder: EncoderKind::None,
+    };
+    let expand_str = |template: &[&str], ctx: &Context| -> Result<Vec<String>> {
+        Ok(expand(template, ctx)?
+            .args
+            .into_iter()
+            .map(|arg| arg.into_string().unwrap())
+            .collect())
+    };
+
+    assert_eq!(
+        expand_str(&["-i", "{1}", "-c", "copy", "{2}"], &ctx).unwrap(),
+        vec!["-i", "in.mkv", "-c", "copy", "out.mp4"]
+    );
+    assert_eq!(
+        expand_str(&["{@}", "--all={@}"], &ctx).unwrap(),
+        vec!["in.mkv", "out.mp4", "--all=in.mkv out.mp4"]
+    );
+    // no placeholders keeps appending the user args
+    assert_eq!(
+        expand_str(&["-exec", "{}", ";"], &ctx).unwrap(),
+        vec!["-exec", "{}", ";", "in.mkv", "out.mp4"]
+    );
+    assert!(expand_str(&["{3}"], &ctx).is_err());
+    assert_eq!(
+        expand_str(&["{99999999999999999999}"], &ctx)
+            .unwrap_err()
+            .to_string(),
+        "No argument for `{99999999999999999999}`, got 2 arguments"
+    );
+    assert!(expand_str(&["{1}"], &ctx).is_err());
+    assert!(expand_str(&["{files}"], &ctx).is_err());
+    assert!(expand_str(&["{env:FLURRY_SURELY_UNSET_VAR}"], &ctx).is_err());
+
+    let files = vec![PathBuf::from("a.mp3"), PathBuf::from("b.mp3")];
+    let scan = Context {
+        args: &args[..1],
+        files: Some(&files),
+        encoder: EncoderKind::None,
+    };
+    assert_eq!(
+        expand_str(&["--playlist", "{files}", "--dir={1}"], &scan).unwrap(),
+        vec!["--playlist", "a.mp3", "b.mp3", "--dir=in.mkv"]
+    );
+    assert_eq!(
+        expand_str(&["--shuffle"], &scan).unwrap(),
+        vec!["--shuffle", "a.mp3", "b.mp3"]
+    );
+}
+
+#[test]
+fn encodes_payloads() {
+    let args = vec!["rust lang".to_owned(), "a&b".to_owned()];
+    let ctx = |encoder| Context {
+        args: &args,
+        files: None,
+        encoder,
+    };
+    let expand_str = |template: &[&str], ctx: &Context| -> Vec<String> {
+        expand(template, ctx)
+            .unwrap()
+            .args
+            .into_iter()
+            .map(|arg| arg.into_string().unwrap())
+            .collect()
+    };
+
+    let url = ctx(EncoderKind::Url);
+    assert_eq!(
+        expand_str(&["https://duckduckgo.com/?q={q}"], &url),
+        vec!["https://duckduckgo.com/?q=rust%20lang%20a%26b"]
+    );
+    assert_eq!(
+        expand_str(&["{1}", "{@}"], &url),
+        vec!["rust%20lang", "rust%20lang", "a%26b"]
+    );
+    assert_eq!(
+        expand_str(&["-v"], &url),
+        vec!["-v", "rust%20lang", "a%26b"]
+    );
+
+    let json = ctx(EncoderKind::Json);
+    assert_eq!(
+        expand_str(&["-d", r#"{"q": {1}}"#, "{@}"], &json),
+        vec!["-d", r#"{"q": "rust lang"}"#, r#"["rust lang","a&b"]"#]
+    );
+    let piped = expand(&["."], &json).unwrap();
+    assert_eq!(piped.args, vec![OsString::from(".")]);
+    assert_eq!(piped.stdin.as_deref(), Some(r#"["rust lang","a&b"]"#));
+
+    let files = vec![PathBuf::from("a.mp3"), PathBuf::from("b.mp3")];
+    let scan = Context {
+        args: &args[..0],
+        files: Some(&files),
+        encoder: EncoderKind::Json,
+    };
+    assert_eq!(
+        expand_str(&["{files}"], &scan),
+        vec![r#"["a.mp3","b.mp3"]"#]
+    );
+    assert_eq!(
+        expand(&["--shuffle"], &scan).unwrap().stdin.as_deref(),
+        Some(r#"["a.mp3","b.mp3"]"#)
+    );
+}
@@ -0,0 +1,28 @@
+use crate::prelude::*;
+use std::{
+    fs::{rename, File},
+    io::Write,
+    path::Path,
+};
+
+/// writes to a sibling temp file and renames it over `path` so readers
+/// never observe a partially written file
+pub fn write_atomic<P: AsRef<Path>, B: AsRef<[u8]>>(path: P, bytes: B) -> Result<()> {
+    let path = path.as_ref();
+    let mut tmp_path = path.as_os_str().to_owned();
+    tmp_path.push(".tmp");
+
+    let mut tmp = File::create(&tmp_path)?;
+    tmp.write_all(bytes.as_ref())?;
+    tmp.sync_all()?;
+    drop(tmp);
+
+    rename(&tmp_path, path)?;
+    if let Some(parent) = path.parent() {
+        // persist the rename itself, best effort on platforms without dir handles
+        if let Ok(dir) = File::open(parent) {
+            dir.sync_all().ok();
+        }
+    }
+    Ok(())
+}
@@ -1 +1,2 @@
+pub mod atomic;
 pub mod recursive;
@@ -16,22 +16,3 @@ macro_rules! seppuku {
         std::process::exit($code);
     }};
 }
-
-#[macro_export]
-macro_rules! run_cmd {
-    (@ cmd:expr =>) => {{ std::process::Command::new($cmd).spawn() }};
-    // spawn raw cmd with variadic args
-    (@ $cmd:expr; $($args:expr),* $(,)?) => {{
-        std::process::Command::new($cmd)
-            .args(&[$($args,)*])
-            .spawn()
-    }};
-    // spawn raw cmd and cwd with variadic args
-    (@ $cmd:expr => $($args:expr),* $(,)?) => {{
-        use std::process::{Command, Stdio};
-        Command::new($cmd)
-            $(.args($args,))*
-            .stdout(Stdio::null())
-            .spawn()
-    }};
-}
@@ -3,4 +3,5 @@ pub mod fs;
 pub mod macros;
 pub mod os;
 pub mod parse;
+pub mod time;
 pub mod traits;
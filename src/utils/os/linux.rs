@@ -1,41 +1,417 @@
+//! freedesktop `.desktop` files, see
+//! https://specifications.freedesktop.org/desktop-entry-spec/latest/
+
 use crate::prelude::*;
+use std::{
+    env::var,
+    fs::{read_dir, read_to_string},
+    path::{Path, PathBuf},
+};
+
+const ENTRY_GROUP: &str = "Desktop Entry";
+
+/// `key=value` entries by group, in the order the groups appear
+#[derive(Debug, Default, PartialEq)]
+pub struct DesktopFile {
+    groups: Vec<(String, HashMap<String, String>)>,
+}
+
+impl DesktopFile {
+    pub fn parse(raw: &str) -> Result<Self> {
+        let mut groups: Vec<(String, HashMap<String, String>)> = vec![];
+        for (n, line) in raw
+            .lines()
+            .enumerate()
+            .map(|(n, line)| (n + 1, line.trim()))
+        {
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            if let Some(group) = line
+                .strip_prefix('[')
+                .and_then(|line| line.strip_suffix(']'))
+            {
+                if groups.iter().any(|(name, _)| name == group) {
+                    bail!("line {}: group [{}] appears twice", n, group);
+                }
+                groups.push((group.to_owned(), HashMap::new()));
+                continue;
+            }
+            let (key, value) = match line.split_once('=') {
+                Some((key, value)) => (key.trim_end(), value.trim_start()),
+                None => bail!("line {}: expected a [group] or key=value", n),
+            };
+            match groups.last_mut() {
+                Some((_, entries)) => entries.insert(key.to_owned(), value.to_owned()),
+                None => bail!("line {}: `{}` comes before the first group", n, key),
+            };
+        }
+        Ok(Self { groups })
+    }
+
+    fn group(&self, group: &str) -> Option<&HashMap<String, String>> {
+        self.groups
+            .iter()
+            .find(|(name, _)| name == group)
+            .map(|(_, entries)| entries)
+    }
+
+    /// the raw value, escapes included
+    pub fn get(&self, group: &str, key: &str) -> Option<&str> {
+        self.group(group)?.get(key).map(String::as_str)
+    }
+
+    pub fn get_bool(&self, group: &str, key: &str) -> bool {
+        self.get(group, key) == Some("true")
+    }
+
+    /// `key[locale]` for the best match of `locale` (as in $LANG), falling back to `key`
+    pub fn get_localized(&self, group: &str, key: &str, locale: Option<&str>) -> Option<String> {
+        let entries = self.group(group)?;
+        locale
+            .map(locale_variants)
+            .into_iter()
+            .flatten()
+            .find_map(|variant| entries.get(&format!("{}[{}]", key, variant)))
+            .or_else(|| entries.get(key))
+            .map(|value| unescape(value))
+    }
+}
+
+/// `lang_COUNTRY@MODIFIER`, `lang_COUNTRY`, `lang@MODIFIER` and `lang`, most specific first
+fn locale_variants(locale: &str) -> Vec<String> {
+    // the encoding never takes part in matching
+    let (locale, modifier) = match locale.split_once('@') {
+        Some((locale, modifier)) => (locale, Some(modifier)),
+        None => (locale, None),
+    };
+    let locale = locale.split('.').next().unwrap_or_default();
+    let (lang, country) = match locale.split_once('_') {
+        Some((lang, country)) => (lang, Some(country)),
+        None => (locale, None),
+    };
 
-pub fn desktop_file_to_exec(mime_dfl: String) -> Result<String> {
-    use std::fs::read;
-
-    let desktop_file = mime_dfl.trim_end_matches("\n");
-    let desktop_file_path = "/usr/share/applications/".to_owned() + desktop_file;
-    let global_install = read(desktop_file_path)?;
-    match find_exec_by_bytes(global_install) {
-        Some(res) => Ok(res),
-        _ => {
-            let mut local_desktop_file_path = super::home();
-            local_desktop_file_path.push(".local/share/applications/");
-            local_desktop_file_path.push(desktop_file);
-            let local_install = read(local_desktop_file_path)?;
-
-            match find_exec_by_bytes(local_install) {
-                Some(res) => Ok(res),
-                _ => bail!("Unable to find exec cmd for {}", mime_dfl),
+    let mut variants = vec![];
+    if let (Some(country), Some(modifier)) = (country, modifier) {
+        variants.push(format!("{}_{}@{}", lang, country, modifier));
+    }
+    if let Some(country) = country {
+        variants.push(format!("{}_{}", lang, country));
+    }
+    if let Some(modifier) = modifier {
+        variants.push(format!("{}@{}", lang, modifier));
+    }
+    variants.push(lang.to_owned());
+    variants
+}
+
+/// escapes shared by every string value: `\s`, `\n`, `\t`, `\r` and `\\`
+fn unescape(value: &str) -> String {
+    let mut unescaped = String::with_capacity(value.len());
+    let mut chars = value.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            unescaped.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('s') => unescaped.push(' '),
+            Some('n') => unescaped.push('\n'),
+            Some('t') => unescaped.push('\t'),
+            Some('r') => unescaped.push('\r'),
+            Some('\\') => unescaped.push('\\'),
+            Some(c) => {
+                // left for the Exec quoting rules, which have escapes of their own
+                unescaped.push('\\');
+                unescaped.push(c);
             }
+            None => unescaped.push('\\'),
         }
     }
+    unescaped
 }
 
-fn find_exec_by_bytes(search_bytes: Vec<u8>) -> Option<String> {
-    let (exec_pat, pat_0) = (b"Exec=", b'E');
-    let (mut start, mut end) = (0, search_bytes.len());
+/// an installed application, from the `[Desktop Entry]` group of its file
+#[derive(Debug, PartialEq)]
+pub struct Application {
+    /// the desktop file id, e.g. `org.gnome.Nautilus` for org.gnome.Nautilus.desktop
+    pub id: String,
+    pub path: PathBuf,
+    pub name: String,
+    /// the Exec line split into arguments, with field codes expanded
+    pub exec: Vec<String>,
+    /// runs in a terminal rather than opening a window of its own
+    pub terminal: bool,
+}
 
-    while start < end {
-        if pat_0 == search_bytes[start] && exec_pat == &search_bytes[start..end] {
-            let wsp = &search_bytes[start..]
-                .iter()
-                .position(|c| c.is_ascii_whitespace())?
-                + start;
-            return String::from_utf8(search_bytes[end..wsp].to_vec()).ok();
+impl Application {
+    /// `None` for entries that aren't applications, are hidden or whose TryExec isn't installed
+    pub fn from_file(
+        id: String,
+        path: PathBuf,
+        file: &DesktopFile,
+        locale: Option<&str>,
+    ) -> Result<Option<Self>> {
+        if file.group(ENTRY_GROUP).is_none() {
+            bail!("{} has no [{}] group", path.display(), ENTRY_GROUP);
         }
-        start += 1;
-        end += 1;
+        if file.get(ENTRY_GROUP, "Type") != Some("Application")
+            || file.get_bool(ENTRY_GROUP, "Hidden")
+            || file.get_bool(ENTRY_GROUP, "NoDisplay")
+        {
+            return Ok(None);
+        }
+        if let Some(try_exec) = file.get(ENTRY_GROUP, "TryExec") {
+            if which::which(unescape(try_exec)).is_err() {
+                return Ok(None);
+            }
+        }
+
+        let name = file
+            .get_localized(ENTRY_GROUP, "Name", locale)
+            .ok_or_else(|| anyhow!("{} has no Name", path.display()))?;
+        let exec = match file.get(ENTRY_GROUP, "Exec") {
+            Some(exec) => unescape(exec),
+            // launched over D-Bus only
+            None => return Ok(None),
+        };
+        let icon = file.get(ENTRY_GROUP, "Icon").map(unescape);
+        let exec = expand_exec(&exec, &name, icon.as_deref(), &path)
+            .map_err(|e| anyhow!("{}: invalid Exec, {}", path.display(), e))?;
+
+        Ok(Some(Self {
+            id,
+            terminal: file.get_bool(ENTRY_GROUP, "Terminal"),
+            path,
+            name,
+            exec,
+        }))
+    }
+
+    /// the reverse DNS prefix of the id is dropped, org.gnome.Nautilus becomes `nautilus`
+    pub fn key(&self) -> String {
+        let key = match self.id.matches('.').count() {
+            0 | 1 => self.id.as_str(),
+            _ => self.id.rsplit('.').next().unwrap_or_default(),
+        };
+        key.to_lowercase()
     }
-    None
+}
+
+/// splits Exec into arguments and expands its field codes, file and url codes are dropped
+/// since the files come from the arguments the command is run with
+fn expand_exec(
+    exec: &str,
+    name: &str,
+    icon: Option<&str>,
+    path: &Path,
+) -> Result<Vec<String>, String> {
+    let mut args = vec![];
+    for arg in split_exec(exec)? {
+        let mut expanded = String::new();
+        let mut chars = arg.chars();
+        while let Some(c) = chars.next() {
+            if c != '%' {
+                expanded.push(c);
+                continue;
+            }
+            match chars.next() {
+                Some('%') => expanded.push('%'),
+                Some('f') | Some('F') | Some('u') | Some('U') => {}
+                Some('i') => {
+                    if let Some(icon) = icon {
+                        args.push("--icon".to_owned());
+                        expanded.push_str(icon);
+                    }
+                }
+                Some('c') => expanded.push_str(name),
+                Some('k') => expanded.push_str(&path.to_string_lossy()),
+                // deprecated
+                Some('d') | Some('D') | Some('n') | Some('N') | Some('v') | Some('m') => {}
+                Some(c) => return Err(format!("unknown field code %{}", c)),
+                None => return Err("`%` at the end of an argument".into()),
+            }
+        }
+        // a field code on its own expands to nothing rather than an empty argument
+        if !expanded.is_empty() || arg.is_empty() {
+            args.push(expanded);
+        }
+    }
+    if args.is_empty() {
+        return Err("no program to run".into());
+    }
+    Ok(args)
+}
+
+/// arguments are separated by spaces and may be double quoted, escaping `"`, `` ` ``, `$` and
+/// `\` with a backslash
+fn split_exec(exec: &str) -> Result<Vec<String>, String> {
+    let mut args = vec![];
+    let mut arg: Option<String> = None;
+    let mut chars = exec.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            ' ' | '\t' => args.extend(arg.take()),
+            '"' => {
+                let arg = arg.get_or_insert_with(String::new);
+                loop {
+                    match chars.next() {
+                        None => return Err("unterminated quote".into()),
+                        Some('"') => break,
+                        Some('\\') => match chars.next() {
+                            Some(c) => arg.push(c),
+                            None => return Err("unterminated quote".into()),
+                        },
+                        Some(c) => arg.push(c),
+                    }
+                }
+            }
+            c => arg.get_or_insert_with(String::new).push(c),
+        }
+    }
+    args.extend(arg);
+    Ok(args)
+}
+
+/// $XDG_DATA_HOME and then $XDG_DATA_DIRS, so local files shadow the ones installed globally
+fn application_dirs() -> Vec<PathBuf> {
+    let data_home = var("XDG_DATA_HOME")
+        .ok()
+        .map(PathBuf::from)
+        .filter(|dir| dir.is_absolute())
+        .unwrap_or_else(|| super::home().join(".local/share"));
+    let data_dirs = var("XDG_DATA_DIRS")
+        .ok()
+        .filter(|dirs| !dirs.is_empty())
+        .unwrap_or_else(|| "/usr/local/share:/usr/share".into());
+
+    std::iter::once(data_home)
+        .chain(data_dirs.split(':').map(PathBuf::from))
+        .map(|dir| dir.join("applications"))
+        .collect()
+}
+
+/// every `.desktop` file under `dir` by id, subdirectories being part of the id
+fn desktop_files(dir: &Path, prefix: &str, found: &mut Vec<(String, PathBuf)>) {
+    let entries = match read_dir(dir) {
+        Ok(entries) => entries,
+        Err(_) => return,
+    };
+    for path in entries
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+    {
+        let name = match path.file_name().and_then(|name| name.to_str()) {
+            Some(name) => name.to_owned(),
+            None => continue,
+        };
+        if path.is_dir() {
+            desktop_files(&path, &format!("{}{}-", prefix, name), found);
+        } else if let Some(stem) = name.strip_suffix(".desktop") {
+            found.push((format!("{}{}", prefix, stem), path));
+        }
+    }
+}
+
+/// applications installed for the current user, along with the files that couldn't be read
+pub fn installed_applications() -> (Vec<Application>, Vec<anyhow::Error>) {
+    let locale = ["LC_ALL", "LC_MESSAGES", "LANG"]
+        .iter()
+        .filter_map(|var_name| var(var_name).ok())
+        .find(|locale| !locale.is_empty());
+
+    let mut files = vec![];
+    for dir in application_dirs() {
+        desktop_files(&dir, "", &mut files);
+    }
+
+    let (mut apps, mut errors) = (vec![], vec![]);
+    let mut seen = std::collections::HashSet::new();
+    for (id, path) in files {
+        // the first file with an id wins, even when it hides the application
+        if !seen.insert(id.clone()) {
+            continue;
+        }
+        let app = read_to_string(&path)
+            .map_err(|e| anyhow!("Unable to read {}: {}", path.display(), e))
+            .and_then(|raw| {
+                DesktopFile::parse(&raw).map_err(|e| anyhow!("{}: {}", path.display(), e))
+            })
+            .and_then(|file| Application::from_file(id, path, &file, locale.as_deref()));
+        match app {
+            Ok(Some(app)) => apps.push(app),
+            Ok(None) => {}
+            Err(e) => errors.push(e),
+        }
+    }
+    apps.sort_by(|a, b| a.id.cmp(&b.id));
+    (apps, errors)
+}
+
+#[test]
+fn parses_desktop_entries() {
+    let raw = r#"
+# comment
+[Desktop Entry]
+Type=Application
+Name=Image Viewer
+Name[de]=Bildbetrachter
+Name[de_AT]=Bildanzeige
+Icon=viewer
+Exec=viewer --title "%c \"x\"" %i --open\s%U %%
+Terminal=false
+
+[Desktop Action new]
+Name=New Window
+Exec=viewer --new
+"#;
+    let file = DesktopFile::parse(raw).unwrap();
+    assert_eq!(file.get("Desktop Action new", "Exec"), Some("viewer --new"));
+    assert_eq!(
+        file.get_localized(ENTRY_GROUP, "Name", Some("de_AT.UTF-8"))
+            .unwrap(),
+        "Bildanzeige"
+    );
+    assert_eq!(
+        file.get_localized(ENTRY_GROUP, "Name", Some("de_CH@euro"))
+            .unwrap(),
+        "Bildbetrachter"
+    );
+    assert_eq!(
+        file.get_localized(ENTRY_GROUP, "Name", Some("fr_FR"))
+            .unwrap(),
+        "Image Viewer"
+    );
+
+    let app = Application::from_file(
+        "org.example.Viewer".into(),
+        "/usr/share/applications/org.example.Viewer.desktop".into(),
+        &file,
+        None,
+    )
+    .unwrap()
+    .unwrap();
+    assert_eq!(app.key(), "viewer");
+    assert_eq!(
+        app.exec,
+        vec![
+            "viewer",
+            "--title",
+            "Image Viewer \"x\"",
+            "--icon",
+            "viewer",
+            "--open",
+            "%"
+        ]
+    );
+    assert!(!app.terminal);
+
+    // `\\` is a string escape, the Exec quoting rules then unescape what it leaves
+    assert_eq!(unescape(r"C:\\x\s\q"), r"C:\x \q");
+    let file = DesktopFile::parse("[Desktop Entry]\nExec=run \"a\\\\\\\\b\"\n").unwrap();
+    let exec = unescape(file.get(ENTRY_GROUP, "Exec").unwrap());
+    assert_eq!(split_exec(&exec).unwrap(), vec!["run", r"a\b"]);
+
+    assert!(DesktopFile::parse("Name=orphan\n").is_err());
+    assert!(split_exec("viewer \"open").is_err());
 }
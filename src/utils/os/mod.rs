@@ -1,4 +1,3 @@
-#[allow(dead_code)]
 pub mod linux;
 
 use crate::prelude::*;
@@ -19,6 +18,14 @@ pub fn query_stdin<Q: std::fmt::Display>(query: Q) -> Option<String> {
     }
 }
 
+/// yes/no question defaulting to no
+pub fn confirm<Q: std::fmt::Display>(query: Q) -> bool {
+    matches!(
+        query_stdin(format!("{} [y/N]", query)).map(|answer| answer.to_lowercase()).as_deref(),
+        Some("y") | Some("yes")
+    )
+}
+
 pub fn home() -> std::path::PathBuf {
     home::home_dir().seppuku("Unable to find user home")
 }
@@ -1,4 +1,12 @@
-use crate::config::types::{EncoderKind, FileTypeFilter, FilterKind, PermissionsKind, ScanDirKind};
+use crate::{
+    apps::{import::OnConflict, sync::Side},
+    config::{
+        format::Format,
+        shell::Shell,
+        types::{EncoderKind, FileTypeFilter, FilterKind, PermissionsKind, ScanDirKind},
+    },
+};
+use tui::style::Color;
 
 pub fn parse_with_delim<S: AsRef<str>>(arg: S, delimiter: &str) -> Option<Vec<String>> {
     let split: Vec<String> = arg
@@ -55,6 +63,45 @@ pub fn encoder_from_arg(arg: &str) -> Result<EncoderKind, String> {
     }
 }
 
+pub fn format_from_arg(arg: &str) -> Result<Format, String> {
+    match arg.trim().to_lowercase().as_str() {
+        "toml" => Ok(Format::Toml),
+        "json" => Ok(Format::Json),
+        "rkyv" | "db" => Ok(Format::Rkyv),
+        _ => Err(String::from("valid inputs are toml, json, rkyv")),
+    }
+}
+
+pub fn shell_from_arg(arg: &str) -> Result<Shell, String> {
+    match arg.trim().to_lowercase().as_str() {
+        "bash" => Ok(Shell::Bash),
+        "zsh" => Ok(Shell::Zsh),
+        "fish" => Ok(Shell::Fish),
+        _ => Err(String::from("valid inputs are bash, zsh, fish")),
+    }
+}
+
+pub fn on_conflict_from_arg(arg: &str) -> Result<OnConflict, String> {
+    match arg.trim() {
+        "fail" => Ok(OnConflict::Fail),
+        "overwrite" => Ok(OnConflict::Overwrite),
+        "prompt" => Ok(OnConflict::Prompt),
+        "rename-suffix" | "rename" => Ok(OnConflict::RenameSuffix),
+        "skip" => Ok(OnConflict::Skip),
+        _ => Err(String::from(
+            "valid inputs are skip, overwrite, rename-suffix, prompt, fail",
+        )),
+    }
+}
+
+pub fn side_from_arg(arg: &str) -> Result<Side, String> {
+    match arg.trim() {
+        "local" => Ok(Side::Local),
+        "shared" => Ok(Side::Shared),
+        _ => Err(String::from("valid inputs are local, shared")),
+    }
+}
+
 pub fn permissions_from_arg(arg: &str) -> Result<PermissionsKind, String> {
     match arg.trim() {
         "group" => Ok(PermissionsKind::Group),
@@ -84,3 +131,103 @@ pub fn file_type_filter_from_arg(arg: &str) -> Result<FilterKind, String> {
 pub fn args_from_arg(arg: &str) -> Result<Vec<String>, String> {
     parse_with_delim(arg, " ").ok_or("no args provided!".into())
 }
+
+pub fn regex_from_arg(arg: &str) -> Result<String, String> {
+    regex::Regex::new(arg)
+        .map(|_| arg.to_owned())
+        .map_err(|e| e.to_string())
+}
+
+pub fn tag_from_arg(arg: &str) -> Result<String, String> {
+    let tag = arg.trim().to_lowercase();
+    if tag.is_empty() || tag.chars().any(|c| c.is_whitespace() || c == ',') {
+        Err(String::from(
+            "tags can't be empty or contain spaces or commas",
+        ))
+    } else {
+        Ok(tag)
+    }
+}
+
+/// `KEY=value` sets a variable, `-KEY` unsets it
+pub fn env_from_arg(arg: &str) -> Result<(String, Option<String>), String> {
+    let (key, value) = match arg.strip_prefix('-') {
+        Some(key) => (key, None),
+        None => match arg.split_once('=') {
+            Some((key, value)) => (key, Some(value.to_owned())),
+            None => return Err(format!("{} is not KEY=value or -KEY", arg)),
+        },
+    };
+    if key.is_empty()
+        || key.starts_with(|c: char| c.is_ascii_digit())
+        || !key.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+    {
+        return Err(format!("{} is not a valid variable name", key));
+    }
+    Ok((key.to_owned(), value))
+}
+
+/// whitespace separated `env_from_arg` values, as typed into the tui
+pub fn env_list_from_arg(arg: &str) -> Result<Vec<(String, Option<String>)>, String> {
+    arg.split_whitespace().map(env_from_arg).collect()
+}
+
+pub fn profile_from_arg(arg: &str) -> Result<String, String> {
+    let name = arg.trim().to_lowercase();
+    if !name.is_empty()
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+    {
+        Ok(name)
+    } else {
+        Err(String::from(
+            "profile names may only contain letters, numbers, - and _",
+        ))
+    }
+}
+
+pub fn color_from_arg(arg: &str) -> Result<Color, String> {
+    let name: String = arg
+        .trim()
+        .to_lowercase()
+        .chars()
+        .filter(|c| *c != '-' && *c != '_' && *c != ' ')
+        .collect();
+
+    let color = match name.as_str() {
+        "reset" | "default" => Color::Reset,
+        "black" => Color::Black,
+        "red" => Color::Red,
+        "green" => Color::Green,
+        "yellow" => Color::Yellow,
+        "blue" => Color::Blue,
+        "magenta" => Color::Magenta,
+        "cyan" => Color::Cyan,
+        "gray" | "grey" => Color::Gray,
+        "darkgray" | "darkgrey" => Color::DarkGray,
+        "lightred" => Color::LightRed,
+        "lightgreen" => Color::LightGreen,
+        "lightyellow" => Color::LightYellow,
+        "lightblue" => Color::LightBlue,
+        "lightmagenta" => Color::LightMagenta,
+        "lightcyan" => Color::LightCyan,
+        "white" => Color::White,
+        hex if hex.starts_with('#') && hex.len() == 7 => {
+            // slicing by bytes below would split multibyte characters
+            if !hex[1..].bytes().all(|b| b.is_ascii_hexdigit()) {
+                return Err(format!("{} is not a valid hex color", arg));
+            }
+            let channel = |i: usize| u8::from_str_radix(&hex[i..i + 2], 16);
+            match (channel(1), channel(3), channel(5)) {
+                (Ok(r), Ok(g), Ok(b)) => Color::Rgb(r, g, b),
+                _ => return Err(format!("{} is not a valid hex color", arg)),
+            }
+        }
+        index => match index.parse::<u8>() {
+            Ok(index) => Color::Indexed(index),
+            _ => return Err(format!("{} is not a color name, #rrggbb or 0-255", arg)),
+        },
+    };
+    Ok(color)
+}
@@ -0,0 +1,42 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+pub fn now_millis() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or_default()
+}
+
+/// formats unix millis as `YYYY-MM-DD HH:MM:SS` (UTC)
+pub fn fmt_utc(millis: u64) -> String {
+    let secs = millis / 1000;
+    let (days, rem) = ((secs / 86_400) as i64, secs % 86_400);
+
+    // civil-from-days, see http://howardhinnant.github.io/date_algorithms.html
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z - era * 146_097;
+    let yoe = (doe - doe / 1_460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+
+    format!(
+        "{:04}-{:02}-{:02} {:02}:{:02}:{:02}",
+        year,
+        month,
+        day,
+        rem / 3_600,
+        rem % 3_600 / 60,
+        rem % 60
+    )
+}
+
+#[test]
+fn utc_formatting() {
+    assert_eq!(fmt_utc(0), "1970-01-01 00:00:00");
+    assert_eq!(fmt_utc(951_782_400_000), "2000-02-29 00:00:00");
+    assert_eq!(fmt_utc(1_626_048_000_000 + 3_723_000), "2021-07-12 01:02:03");
+}